@@ -0,0 +1,65 @@
+//! Compares the `Vec` and rope stack storage on a generated input with
+//! many large moves. Run it with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+// The moves are generated the same way as in the tests.
+use supply_stacks::{
+    test_util::{random_moves, Lcg},
+    CraneInstructions, CraneModel, RopeStacks, Stack, Stacks,
+};
+
+const NUM_STACKS: usize = 9;
+const CRATES_PER_STACK: usize = 20_000;
+const NUM_MOVES: usize = 200_000;
+
+/// Stacks of letters and a list of valid moves, from a fixed seed so
+/// every run does the same work.
+fn stress_input() -> (Stacks, CraneInstructions) {
+    let mut rng = Lcg::new(2022);
+    let stacks: Stacks = (0..NUM_STACKS)
+        .map(|_| {
+            let crates: Vec<char> = (0..CRATES_PER_STACK)
+                .map(|_| char::from(b'A' + u8::try_from(rng.next(26)).unwrap()))
+                .collect();
+            Stack::from(crates)
+        })
+        .collect();
+    let instructions = random_moves(&mut rng, &stacks, NUM_MOVES);
+    (stacks, instructions)
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn main() {
+    let (stacks, instructions) = stress_input();
+    let crates_moved: usize = instructions.iter().map(|i| i.num_to_move).sum();
+    println!(
+        "{NUM_STACKS} stacks of {CRATES_PER_STACK} crates, {NUM_MOVES} moves, \
+         {crates_moved} crates moved"
+    );
+
+    for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+        let (vec_tops, vec_time) = time(|| {
+            stacks
+                .clone()
+                .apply_instructions(&crane, &instructions)
+                .unwrap()
+                .tops_string()
+                .unwrap()
+        });
+        let (rope_tops, rope_time) = time(|| {
+            RopeStacks::from(&stacks)
+                .apply_instructions(&crane, &instructions)
+                .unwrap()
+                .tops_string()
+                .unwrap()
+        });
+        assert_eq!(vec_tops, rope_tops, "the backends disagree");
+        println!("{crane:?}: vec {vec_time:?}, rope {rope_time:?} ({vec_tops})");
+    }
+}
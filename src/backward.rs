@@ -0,0 +1,195 @@
+//! Working out the top crates without moving any crates.
+//!
+//! Both parts of the puzzle only ask for `tops_string`, but simulating
+//! the instructions moves every crate. Instead we can start from the top
+//! of each stack at the end and walk the instructions backwards, keeping
+//! track of where that crate must have been before each one. Only the
+//! stack heights are needed along the way, so this takes time
+//! proportional to the number of instructions times the number of
+//! stacks, however many crates each instruction moves.
+
+use crate::{
+    crane::{Crane, CraneInstruction, CraneInstructions, Landing},
+    error::CraneError,
+    model::{Stack, Stacks},
+    parse::PuzzleInput,
+};
+
+/// Where a crate is: its stack, and how many crates are above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    stack: usize,
+    depth: usize,
+}
+
+impl Position {
+    /// Where the crate at this position was just before `instruction`.
+    const fn before(self, instruction: &CraneInstruction, landing: Landing) -> Self {
+        let CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        } = *instruction;
+        if self.stack == to_stack && self.depth < num_to_move {
+            // One of the moved crates, which was in the group on top of
+            // the source stack.
+            let depth = match landing {
+                Landing::InOrder => self.depth,
+                Landing::Reversed => num_to_move - 1 - self.depth,
+            };
+            Self {
+                stack: from_stack,
+                depth,
+            }
+        } else if from_stack == to_stack {
+            self
+        } else if self.stack == to_stack {
+            Self {
+                stack: self.stack,
+                depth: self.depth - num_to_move,
+            }
+        } else if self.stack == from_stack {
+            Self {
+                stack: self.stack,
+                depth: self.depth + num_to_move,
+            }
+        } else {
+            self
+        }
+    }
+}
+
+impl Stacks {
+    /// The `tops_string` of these stacks after applying `instructions`,
+    /// worked out backwards from the end without moving any crates.
+    ///
+    /// This needs to know how the crane lands groups of crates, so for a
+    /// crane without a `Landing` it falls back to simulating them, as it
+    /// does for stacks with capacity limits and cranes with lift limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as applying the instructions and then
+    /// calling `tops_string` would.
+    pub fn tops_after<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        instructions: &CraneInstructions,
+    ) -> Result<String, CraneError> {
+        let simulate = || {
+            self.clone()
+                .apply_instructions(crane, instructions)?
+                .tops_string()
+        };
+        let Some(landing) = crane.landing() else {
+            return simulate();
+        };
+        if !self.capacity().is_unlimited() || crane.lift_limit().is_some() {
+            return simulate();
+        }
+
+        let mut heights: Vec<usize> = self.iter().map(Stack::len).collect();
+        for instruction in instructions.iter() {
+            if instruction
+                .check::<char>(heights.len(), |stack| heights[stack])
+                .is_err()
+            {
+                // Simulating reports the error with the stacks as they
+                // were, just like `apply_instructions`.
+                return simulate();
+            }
+            heights[instruction.from_stack] -= instruction.num_to_move;
+            heights[instruction.to_stack] += instruction.num_to_move;
+        }
+        if let Some(stack) = heights.iter().position(|&height| height == 0) {
+            return Err(CraneError::EmptyStack { stack });
+        }
+
+        let mut positions: Vec<Position> = (0..self.len())
+            .map(|stack| Position { stack, depth: 0 })
+            .collect();
+        for instruction in instructions.iter().rev() {
+            for position in &mut positions {
+                *position = position.before(instruction, landing);
+            }
+        }
+        Ok(positions
+            .iter()
+            .map(|position| {
+                let stack = &self.stacks[position.stack].stack;
+                stack[stack.len() - 1 - position.depth]
+            })
+            .collect())
+    }
+}
+
+impl PuzzleInput {
+    /// The top crates after applying all the instructions, worked out with
+    /// `Stacks::tops_after`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `run`, and `CraneError::EmptyStack` if a
+    /// stack ends up empty.
+    pub fn tops<C: Crane + ?Sized>(&self, crane: &C) -> Result<String, CraneError> {
+        self.stacks
+            .tops_after(crane, &self.instructions)
+            .map_err(|error| self.locate_error(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        crane::{CraneModel, CrateMover9000, CrateMover9001},
+        test_util::TopOnly,
+    };
+
+    fn simulated<C: Crane>(input: &PuzzleInput, crane: &C) -> Result<String, CraneError> {
+        input.run(crane)?.tops_string()
+    }
+
+    #[test]
+    fn test_matches_simulation() {
+        let contents = fs::read_to_string("input.txt").unwrap();
+        let input: PuzzleInput = contents.parse().unwrap();
+        assert_eq!("SBPQRSCDF", input.tops(&CrateMover9000).unwrap());
+        assert_eq!("RGLVRCQSB", input.tops(&CrateMover9001).unwrap());
+
+        let input: PuzzleInput = "1 A B C\n2 D\n3 E F\n\n\
+                                  move 3 from 1 to 1\n\
+                                  move 2 from 1 to 3\n\
+                                  move 4 from 3 to 2\n\
+                                  move 2 from 2 to 1\n\
+                                  move 1 from 3 to 2\n"
+            .parse()
+            .unwrap();
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            assert_eq!(simulated(&input, &crane), input.tops(&crane));
+        }
+    }
+
+    #[test]
+    fn test_errors_match_simulation() {
+        let bad_move: PuzzleInput = "1 A B\n2 C\n\nmove 1 from 1 to 2\nmove 3 from 1 to 2\n"
+            .parse()
+            .unwrap();
+        let empty_at_end: PuzzleInput = "1 A B\n2 C\n\nmove 2 from 1 to 2\n".parse().unwrap();
+        for input in [bad_move, empty_at_end] {
+            assert_eq!(
+                simulated(&input, &CrateMover9000),
+                input.tops(&CrateMover9000)
+            );
+        }
+    }
+
+    // Without a `Landing`, the crane's moves have to be simulated.
+    #[test]
+    fn test_crane_without_landing() {
+        let input: PuzzleInput = "1 A B C\n2 D\n\nmove 2 from 1 to 2\n".parse().unwrap();
+        assert_eq!(Ok("BC".to_string()), input.tops(&TopOnly));
+    }
+}
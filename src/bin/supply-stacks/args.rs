@@ -0,0 +1,374 @@
+//! Command line argument parsing for `supply-stacks`.
+
+use supply_stacks::{
+    CraneModel, LiftLimit, LiftPolicy, Limited, OverflowPolicy, ParseMode, StackBackend,
+    StackFormat,
+};
+
+pub const USAGE: &str = "\
+Usage: supply-stacks <COMMAND> [OPTIONS] [INPUT]
+
+Commands:
+  run [INPUT]        Apply the instructions and print the top crate of each stack
+  validate [INPUT]   Check that the input parses and every instruction can be applied
+  render [INPUT]     Draw the stacks as columns (after the instructions with --final)
+  convert [INPUT]    Rewrite the input with the stacks in the other format
+  trace [INPUT]      Print the crates moved and stack heights after every instruction
+  cost [INPUT]       Estimate how long the crane takes over the instructions, in seconds
+  diff <A> <B>       Compare the final stacks of two inputs
+
+INPUT is a file in the puzzle input format; use `-` or leave it off to read stdin.
+
+Options:
+  --crane <9000|9001>      The crane to simulate [default: 9000]
+  --backend <vec|rope>     Stack storage; `rope` is faster for huge moves [default: vec]
+  --lenient                Accept any instruction line with exactly three numbers
+  --overflow <reject|clamp>
+                           Fail on moves past a stack's capacity, or move only what fits
+                           [default: reject]
+  --max-lift <N>           The most crates the crane can lift at once [default: no limit]
+  --split-lifts            Make moves over --max-lift as several smaller lifts, not an error
+  --backward               (run) Work out the tops backwards, without moving any crates
+  --stream                 (run) Apply instructions as they're read, for inputs too big for memory
+  --final                  (render) Draw the stacks after applying the instructions
+  --width <N>              (render) Characters per stack column [default: 4]
+  --pad                    (render) Pad rows with trailing spaces to the full width
+  --to <lines|drawing>     (convert) The stack format to write [default: the other one]
+  -h, --help               Print this message
+
+Options that would make no difference to the command are an error.
+
+Exit status:
+  0  success
+  1  an input couldn't be read
+  2  invalid command line arguments
+  3  an input couldn't be parsed
+  4  the instructions couldn't be applied to the stacks
+  5  (diff) the final stacks are different
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    Validate,
+    Render,
+    Convert,
+    Trace,
+    Cost,
+    Diff,
+}
+
+/// How `run` works out the top crates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunMethod {
+    /// Read the whole input and apply the instructions.
+    Simulate,
+    /// Walk the instructions backwards (`--backward`).
+    Backward,
+    /// Apply the instructions as they're read (`--stream`).
+    Stream,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    pub command: Command,
+    /// Input paths, where `-` means stdin.
+    pub inputs: Vec<String>,
+    pub crane: CraneModel,
+    pub backend: StackBackend,
+    pub mode: ParseMode,
+    pub overflow: OverflowPolicy,
+    pub lift: LiftLimit,
+    pub method: RunMethod,
+    pub final_state: bool,
+    pub width: Option<usize>,
+    pub pad: bool,
+    pub to: Option<StackFormat>,
+}
+
+/// Why the arguments couldn't be parsed. `Help` isn't really an error,
+/// but like the others it means we print the usage and stop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    Help,
+    Invalid(String),
+}
+
+impl Args {
+    /// Parse the arguments, not including the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ArgsError> {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            None | Some("-h" | "--help" | "help") => return Err(ArgsError::Help),
+            Some("run") => Command::Run,
+            Some("validate") => Command::Validate,
+            Some("render") => Command::Render,
+            Some("convert") => Command::Convert,
+            Some("trace") => Command::Trace,
+            Some("cost") => Command::Cost,
+            Some("diff") => Command::Diff,
+            Some(other) => return Err(invalid(format!("unknown command `{other}`"))),
+        };
+
+        // The options given, to check they all make a difference.
+        let mut given = Vec::new();
+        let mut parsed = Self {
+            command,
+            inputs: Vec::new(),
+            crane: CraneModel::default(),
+            backend: StackBackend::default(),
+            mode: ParseMode::Strict,
+            overflow: OverflowPolicy::Reject,
+            lift: LiftLimit::new(),
+            method: RunMethod::Simulate,
+            final_state: false,
+            width: None,
+            pad: false,
+            to: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| invalid(format!("`{flag}` needs a value")))
+            };
+            if arg.starts_with("--") {
+                given.push(arg.clone());
+            }
+            match arg.as_str() {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "--crane" => {
+                    parsed.crane = match value("--crane")?.as_str() {
+                        "9000" => CraneModel::CrateMover9000,
+                        "9001" => CraneModel::CrateMover9001,
+                        other => return Err(invalid(format!("unknown crane `{other}`"))),
+                    };
+                }
+                "--backend" => {
+                    parsed.backend = match value("--backend")?.as_str() {
+                        "vec" => StackBackend::Vec,
+                        "rope" => StackBackend::Rope,
+                        other => return Err(invalid(format!("unknown backend `{other}`"))),
+                    };
+                }
+                "--lenient" => parsed.mode = ParseMode::Lenient,
+                "--overflow" => {
+                    parsed.overflow = match value("--overflow")?.as_str() {
+                        "reject" => OverflowPolicy::Reject,
+                        "clamp" => OverflowPolicy::Clamp,
+                        other => return Err(invalid(format!("unknown overflow policy `{other}`"))),
+                    };
+                }
+                "--max-lift" => {
+                    let max = value("--max-lift")?;
+                    parsed.lift.max_crates = Some(
+                        max.parse()
+                            .map_err(|_| invalid(format!("invalid lift limit `{max}`")))?,
+                    );
+                }
+                "--split-lifts" => parsed.lift.policy = LiftPolicy::Split,
+                "--backward" => parsed.set_method(RunMethod::Backward)?,
+                "--stream" => parsed.set_method(RunMethod::Stream)?,
+                "--final" => parsed.final_state = true,
+                "--width" => {
+                    let width = value("--width")?;
+                    parsed.width = Some(
+                        width
+                            .parse()
+                            .map_err(|_| invalid(format!("invalid width `{width}`")))?,
+                    );
+                }
+                "--pad" => parsed.pad = true,
+                "--to" => {
+                    parsed.to = Some(match value("--to")?.as_str() {
+                        "lines" => StackFormat::Lines,
+                        "drawing" => StackFormat::Drawing,
+                        other => return Err(invalid(format!("unknown format `{other}`"))),
+                    });
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(invalid(format!("unknown option `{flag}`")));
+                }
+                _ => parsed.inputs.push(arg),
+            }
+        }
+
+        parsed.check(&given)?;
+        Ok(parsed)
+    }
+
+    /// Check the inputs, and that every option in `given` applies to the
+    /// command. With no inputs, read stdin.
+    fn check(&mut self, given: &[String]) -> Result<(), ArgsError> {
+        match (self.command, self.inputs.len()) {
+            (Command::Diff, 2) => {}
+            (Command::Diff, _) => return Err(invalid("`diff` needs two inputs".to_string())),
+            (_, 0) => self.inputs.push("-".to_string()),
+            (_, 1) => {}
+            (_, _) => return Err(invalid("expected at most one input".to_string())),
+        }
+        // Stdin can only be read once.
+        if self.inputs.iter().filter(|input| *input == "-").count() > 1 {
+            return Err(invalid("only one input can be `-`".to_string()));
+        }
+        for flag in given {
+            self.check_applies(flag)?;
+        }
+        Ok(())
+    }
+
+    /// Make sure `flag` changes what the command does, rather than being
+    /// silently ignored.
+    fn check_applies(&self, flag: &str) -> Result<(), ArgsError> {
+        let (applies, used_with) = match flag {
+            "--backward" | "--stream" => (self.command == Command::Run, "`run`"),
+            "--final" | "--width" | "--pad" => (self.command == Command::Render, "`render`"),
+            "--to" => (self.command == Command::Convert, "`convert`"),
+            // Only some commands apply the instructions.
+            "--crane" | "--overflow" | "--max-lift" | "--split-lifts" => (
+                match self.command {
+                    Command::Convert => false,
+                    Command::Render => self.final_state,
+                    _ => true,
+                },
+                "commands that apply the instructions",
+            ),
+            // And only some of those keep every stack in memory.
+            "--backend" => (
+                match self.command {
+                    Command::Run => self.method == RunMethod::Simulate,
+                    Command::Render => self.final_state,
+                    Command::Validate | Command::Diff => true,
+                    Command::Convert | Command::Trace | Command::Cost => false,
+                },
+                "`run`, `validate`, `render --final` and `diff`, without \
+                 `--backward` or `--stream`",
+            ),
+            _ => (true, ""),
+        };
+        if applies {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "`{flag}` can only be used with {used_with}"
+            )))
+        }
+    }
+
+    /// The chosen crane, with the lift limit if there is one.
+    pub const fn crane(&self) -> Limited<CraneModel> {
+        Limited::new(self.crane, self.lift)
+    }
+
+    fn set_method(&mut self, method: RunMethod) -> Result<(), ArgsError> {
+        if self.method != RunMethod::Simulate && self.method != method {
+            return Err(invalid(
+                "`--backward` and `--stream` can't be used together".to_string(),
+            ));
+        }
+        self.method = method;
+        Ok(())
+    }
+}
+
+const fn invalid(message: String) -> ArgsError {
+    ArgsError::Invalid(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, ArgsError> {
+        Args::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = parse("run").unwrap();
+        assert_eq!(Command::Run, args.command);
+        assert_eq!(vec!["-"], args.inputs);
+        assert_eq!(CraneModel::CrateMover9000, args.crane);
+        assert_eq!(ParseMode::Strict, args.mode);
+    }
+
+    #[test]
+    fn test_options() {
+        let args = parse("render input.txt --crane 9001 --final --width 6 --pad").unwrap();
+        assert_eq!(Command::Render, args.command);
+        assert_eq!(vec!["input.txt"], args.inputs);
+        assert_eq!(CraneModel::CrateMover9001, args.crane);
+        assert!(args.final_state && args.pad);
+        assert_eq!(Some(6), args.width);
+
+        let args = parse("run --backend rope").unwrap();
+        assert_eq!(StackBackend::Rope, args.backend);
+        assert_eq!(RunMethod::Backward, parse("run --backward").unwrap().method);
+        assert_eq!(RunMethod::Stream, parse("run --stream").unwrap().method);
+        assert_eq!(Command::Cost, parse("cost input.txt").unwrap().command);
+
+        let args = parse("convert --to drawing --lenient").unwrap();
+        assert_eq!(Some(StackFormat::Drawing), args.to);
+        assert_eq!(ParseMode::Lenient, args.mode);
+        let args = parse("trace --overflow clamp").unwrap();
+        assert_eq!(OverflowPolicy::Clamp, args.overflow);
+
+        let args = parse("run --max-lift 3 --split-lifts").unwrap();
+        assert_eq!(
+            LiftLimit::new().max_crates(3).policy(LiftPolicy::Split),
+            args.lift
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Err(ArgsError::Help), parse(""));
+        assert_eq!(Err(ArgsError::Help), parse("run --help"));
+        assert!(matches!(parse("fly"), Err(ArgsError::Invalid(_))));
+        assert!(matches!(
+            parse("run --crane 9002"),
+            Err(ArgsError::Invalid(_))
+        ));
+        assert!(matches!(parse("run --crane"), Err(ArgsError::Invalid(_))));
+        assert!(matches!(parse("run a b"), Err(ArgsError::Invalid(_))));
+        assert!(matches!(parse("diff a"), Err(ArgsError::Invalid(_))));
+        assert!(matches!(
+            parse("run --max-lift some"),
+            Err(ArgsError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse("run --stream --backward"),
+            Err(ArgsError::Invalid(_))
+        ));
+        assert_eq!(vec!["a", "b"], parse("diff a b").unwrap().inputs);
+        assert!(matches!(parse("diff - -"), Err(ArgsError::Invalid(_))));
+        assert_eq!(vec!["-", "b"], parse("diff - b").unwrap().inputs);
+    }
+
+    #[test]
+    fn test_options_that_do_nothing() {
+        for args in [
+            "run --backend rope --backward",
+            "run --backend rope --stream",
+            "trace --backend rope",
+            "run --final",
+            "run --width 6",
+            "validate --pad",
+            "run --to lines",
+            "render --to drawing",
+            "cost --backward",
+            "convert --crane 9001",
+            "render --max-lift 2",
+        ] {
+            assert!(matches!(parse(args), Err(ArgsError::Invalid(_))), "{args}");
+        }
+        assert_eq!(
+            Err(invalid(
+                "`--to` can only be used with `convert`".to_string()
+            )),
+            parse("run --to lines")
+        );
+        assert!(parse("render --final --backend rope --crane 9001").is_ok());
+        assert!(parse("diff a b --backend rope --lenient").is_ok());
+    }
+}
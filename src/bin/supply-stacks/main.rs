@@ -0,0 +1,273 @@
+//! The `supply-stacks` command line tool: runs, checks, and converts puzzle
+//! inputs using the `supply_stacks` library. Run it with `--help` for usage.
+
+mod args;
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    process::ExitCode,
+};
+
+use args::{Args, ArgsError, Command, RunMethod, USAGE};
+use supply_stacks::{
+    CraneError, Diagnostic, InputStream, LinearCost, ParseError, PuzzleInput, StackFormat, Stacks,
+    StreamError,
+};
+
+/// The ways the tool can fail, each with its own exit code so scripts
+/// can tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// An input couldn't be read.
+    Io,
+    /// The command line arguments were wrong.
+    Usage,
+    /// An input isn't valid.
+    Parse,
+    /// The instructions couldn't be applied to the stacks.
+    Crane,
+    /// `diff` found the two inputs end up different.
+    Different,
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> Self {
+        Self::from(match failure {
+            Failure::Io => 1,
+            Failure::Usage => 2,
+            Failure::Parse => 3,
+            Failure::Crane => 4,
+            Failure::Different => 5,
+        })
+    }
+}
+
+/// An input file and the name to use for it in error messages.
+struct Source {
+    name: String,
+    contents: String,
+}
+
+impl Source {
+    fn read(path: &str) -> Result<Self, Failure> {
+        let (name, contents) = if path == "-" {
+            let mut contents = String::new();
+            let result = io::stdin().read_to_string(&mut contents);
+            ("<stdin>".to_string(), result.map(|_| contents))
+        } else {
+            (path.to_string(), fs::read_to_string(path))
+        };
+        match contents {
+            Ok(contents) => Ok(Self { name, contents }),
+            Err(error) => {
+                eprintln!("error: failed to read {name}: {error}");
+                Err(Failure::Io)
+            }
+        }
+    }
+
+    fn parse(&self, args: &Args) -> Result<PuzzleInput, Failure> {
+        let mut input = PuzzleInput::parse_with(&self.contents, args.mode).map_err(|errors| {
+            for error in &errors {
+                self.report_parse_error(error);
+            }
+            Failure::Parse
+        })?;
+        input.stacks.set_overflow_policy(args.overflow);
+        Ok(input)
+    }
+
+    fn run(&self, input: &PuzzleInput, args: &Args) -> Result<Stacks, Failure> {
+        input
+            .run_with(&args.crane(), args.backend)
+            .map_err(|error| {
+                self.report_crane_error(&error);
+                Failure::Crane
+            })
+    }
+
+    fn report_parse_error(&self, error: &ParseError) {
+        eprint!(
+            "{}",
+            Diagnostic::from_parse_error(error, &self.name, &self.contents)
+        );
+    }
+
+    fn report_crane_error(&self, error: &CraneError) {
+        match Diagnostic::from_crane_error(error, &self.name, &self.contents) {
+            Some(diagnostic) => eprint!("{diagnostic}"),
+            None => eprintln!("error: {}: {error}", self.name),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(ArgsError::Help) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(ArgsError::Invalid(message)) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return Failure::Usage.into();
+        }
+    };
+
+    match execute(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => failure.into(),
+    }
+}
+
+fn execute(args: &Args) -> Result<(), Failure> {
+    if args.command == Command::Run && args.method == RunMethod::Stream {
+        return run_stream(args);
+    }
+    let source = Source::read(&args.inputs[0])?;
+    let input = source.parse(args)?;
+
+    match args.command {
+        Command::Run => {
+            let tops = if args.method == RunMethod::Backward {
+                input.tops(&args.crane())
+            } else {
+                source.run(&input, args)?.tops_string()
+            };
+            let tops = tops.map_err(|error| {
+                source.report_crane_error(&error);
+                Failure::Crane
+            })?;
+            println!("{tops}");
+        }
+        Command::Validate => {
+            source.run(&input, args)?;
+            println!(
+                "{}: ok ({} stacks, {} instructions)",
+                source.name,
+                input.stacks.len(),
+                input.instructions.len()
+            );
+        }
+        Command::Render => {
+            let stacks = if args.final_state {
+                source.run(&input, args)?
+            } else {
+                input.stacks
+            };
+            let mut drawing = stacks.drawing().pad_rows(args.pad);
+            if let Some(width) = args.width {
+                drawing = drawing.column_width(width);
+            }
+            print!("{drawing}");
+        }
+        Command::Convert => {
+            let format = args
+                .to
+                .unwrap_or_else(|| match StackFormat::detect(&source.contents) {
+                    StackFormat::Lines => StackFormat::Drawing,
+                    StackFormat::Drawing => StackFormat::Lines,
+                });
+            match format {
+                StackFormat::Lines => print!("{}", input.stacks),
+                StackFormat::Drawing => print!("{}", input.stacks.drawing()),
+            }
+            println!();
+            for instruction in input.instructions.iter() {
+                println!("{instruction}");
+            }
+        }
+        Command::Trace => trace(&source, &input, args)?,
+        Command::Cost => {
+            let model = LinearCost::for_crane(args.crane);
+            let report = input.cost_report(&args.crane(), &model).map_err(|error| {
+                source.report_crane_error(&error);
+                Failure::Crane
+            })?;
+            print!("{report}");
+        }
+        Command::Diff => {
+            let first = source.run(&input, args)?;
+            let other = Source::read(&args.inputs[1])?;
+            let second = other.run(&other.parse(args)?, args)?;
+            diff(&first, &second)?;
+        }
+    }
+    Ok(())
+}
+
+/// `run --stream`: apply the instructions without reading the whole
+/// input into memory. Errors can't point at the input like the other
+/// commands do, since its lines are gone by the time we'd show them.
+fn run_stream(args: &Args) -> Result<(), Failure> {
+    let path = &args.inputs[0];
+    let (name, reader): (&str, Box<dyn BufRead>) = if path == "-" {
+        ("<stdin>", Box::new(io::stdin().lock()))
+    } else {
+        match fs::File::open(path) {
+            Ok(file) => (path, Box::new(BufReader::new(file))),
+            Err(error) => {
+                eprintln!("error: failed to read {path}: {error}");
+                return Err(Failure::Io);
+            }
+        }
+    };
+    let tops = InputStream::new(reader, args.mode)
+        .and_then(|mut stream| {
+            stream.set_overflow_policy(args.overflow);
+            stream.run(&args.crane())
+        })
+        .and_then(|stacks| Ok(stacks.tops_string()?));
+    match tops {
+        Ok(tops) => {
+            println!("{tops}");
+            Ok(())
+        }
+        Err(error) => {
+            eprintln!("error: {name}: {error}");
+            Err(match error {
+                StreamError::Io(_) => Failure::Io,
+                StreamError::Parse(_) => Failure::Parse,
+                StreamError::Crane(_) => Failure::Crane,
+            })
+        }
+    }
+}
+
+fn trace(source: &Source, input: &PuzzleInput, args: &Args) -> Result<(), Failure> {
+    print!("initial stacks:\n{}", input.stacks);
+    let crane = args.crane();
+    let mut trace = input.trace(&crane);
+    for step in trace.by_ref() {
+        match step {
+            Ok(step) => println!("{step}"),
+            Err(error) => {
+                source.report_crane_error(&error);
+                return Err(Failure::Crane);
+            }
+        }
+    }
+    print!("final stacks:\n{}", trace.stacks());
+    Ok(())
+}
+
+fn diff(first: &Stacks, second: &Stacks) -> Result<(), Failure> {
+    let mut different = false;
+    for i in 0..first.len().max(second.len()) {
+        let (a, b) = (first.get(i), second.get(i));
+        if a != b {
+            different = true;
+            let show = |stack: Option<_>| {
+                stack.map_or_else(|| "(missing)".to_string(), ToString::to_string)
+            };
+            println!("stack {}: {} | {}", i + 1, show(a), show(b));
+        }
+    }
+    if different {
+        Err(Failure::Different)
+    } else {
+        println!("the final stacks are the same");
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+//! Limits on how many crates each stack can hold.
+//!
+//! Real racks have a maximum height. A stack configuration can declare
+//! one for every stack with a line like `capacity 6`, or for a single
+//! stack with `capacity 4 for 2`. Applying an instruction that would take
+//! a stack past its limit either fails with `CraneError::Overflow` or, if
+//! the [`OverflowPolicy`] says so, moves only as many crates as fit.
+//! A configuration with a stack that already holds more crates than its
+//! limit doesn't parse.
+
+use std::fmt::{self, Display};
+
+use crate::{crane::CraneInstruction, error::CraneError, lift::Lifts, model::Stacks};
+
+/// What to do with an instruction that would overfill a stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OverflowPolicy {
+    /// The instruction fails with `CraneError::Overflow`.
+    #[default]
+    Reject,
+    /// Only the top crates that fit are moved, possibly none at all.
+    Clamp,
+}
+
+/// The height limits of a set of stacks. By default there aren't any.
+///
+/// Parsing makes sure no stack starts over its limit, but `set_capacity`
+/// doesn't; a stack that's over its limit can lose crates but not gain any.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Capacity {
+    /// The limit for every stack that doesn't have its own.
+    pub all: Option<usize>,
+    /// The limits of particular stacks, indexed by stack. Stacks past the
+    /// end of this use `all`.
+    pub per_stack: Vec<Option<usize>>,
+    pub policy: OverflowPolicy,
+}
+
+impl Capacity {
+    /// The most crates stack `stack` can hold, if there's a limit.
+    #[must_use]
+    pub fn limit(&self, stack: usize) -> Option<usize> {
+        self.per_stack.get(stack).copied().flatten().or(self.all)
+    }
+
+    /// Whether there are no limits at all.
+    #[must_use]
+    pub fn is_unlimited(&self) -> bool {
+        self.all.is_none() && self.per_stack.iter().all(Option::is_none)
+    }
+
+    /// How many more crates a stack of height `height` can take.
+    pub(crate) fn room(&self, stack: usize, height: usize) -> usize {
+        self.limit(stack)
+            .map_or(usize::MAX, |limit| limit.saturating_sub(height))
+    }
+
+    /// Set the limit of a single stack.
+    pub fn set_limit(&mut self, stack: usize, limit: usize) {
+        if stack >= self.per_stack.len() {
+            self.per_stack.resize(stack + 1, None);
+        }
+        self.per_stack[stack] = Some(limit);
+    }
+
+    /// The instruction to actually carry out in place of `instruction`,
+    /// which has already been checked against the stacks, where `height`
+    /// gives the number of crates on each stack. Under `Clamp` this moves
+    /// fewer crates if they don't all fit.
+    pub(crate) fn admit<L>(
+        &self,
+        instruction: &CraneInstruction,
+        height: impl FnOnce(usize) -> usize,
+    ) -> Result<CraneInstruction, CraneError<L>> {
+        let stack = instruction.to_stack;
+        let Some(capacity) = self.limit(stack) else {
+            return Ok(*instruction);
+        };
+        // Moving crates within a stack doesn't change its height.
+        if instruction.from_stack == stack {
+            return Ok(*instruction);
+        }
+        let room = self.room(stack, height(stack));
+        if instruction.num_to_move <= room {
+            return Ok(*instruction);
+        }
+        match self.policy {
+            OverflowPolicy::Reject => Err(CraneError::Overflow {
+                stack,
+                capacity,
+                excess: instruction.num_to_move - room,
+            }),
+            OverflowPolicy::Clamp => Ok(CraneInstruction {
+                num_to_move: room,
+                ..*instruction
+            }),
+        }
+    }
+}
+
+// The `capacity` lines that set these limits, each ending in a newline,
+// so they can go before the stacks in either format.
+impl Display for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(limit) = self.all {
+            writeln!(f, "capacity {limit}")?;
+        }
+        for (i, limit) in self.per_stack.iter().enumerate() {
+            if let Some(limit) = limit {
+                writeln!(f, "capacity {limit} for {}", i + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<L> Stacks<L> {
+    /// The height limits of these stacks.
+    #[must_use]
+    pub const fn capacity(&self) -> &Capacity {
+        &self.capacity
+    }
+
+    /// Replace the height limits of these stacks. Stacks that are already
+    /// over their new limit keep their crates, but can't take any more.
+    pub fn set_capacity(&mut self, capacity: Capacity) {
+        self.capacity = capacity;
+    }
+
+    /// Change what happens when an instruction would overfill a stack.
+    pub const fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.capacity.policy = policy;
+    }
+
+    /// The instruction that applying `instruction` will actually carry
+    /// out, which only differs from it when the `Clamp` policy has to
+    /// move fewer crates.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `apply_instruction` would.
+    pub fn admit(&self, instruction: &CraneInstruction) -> Result<CraneInstruction, CraneError<L>> {
+        let lifts = Lifts::plan(
+            instruction,
+            self.len(),
+            &self.capacity,
+            |stack| self.stacks[stack].len(),
+            None,
+            None,
+            |_| 0,
+        )?;
+        Ok(lifts.admitted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crane::{CraneInstructions, CrateMover9000, CrateMover9001},
+        error::ParseErrorKind,
+        parse::PuzzleInput,
+        rope::StackBackend,
+    };
+
+    fn instruction(num_to_move: usize, from_stack: usize, to_stack: usize) -> CraneInstruction {
+        CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        }
+    }
+
+    #[test]
+    fn test_reject() {
+        let stacks: Stacks = "capacity 3\ncapacity 4 for 2\n1 A B C\n2 D\n3 E F"
+            .parse()
+            .unwrap();
+        assert_eq!(Some(3), stacks.capacity().limit(0));
+        assert_eq!(Some(4), stacks.capacity().limit(1));
+
+        assert_eq!(
+            Err(CraneError::Overflow {
+                stack: 2,
+                capacity: 3,
+                excess: 1
+            }),
+            stacks
+                .clone()
+                .apply_instruction(&CrateMover9001, &instruction(2, 0, 2))
+        );
+        let moved = stacks
+            .apply_instruction(&CrateMover9001, &instruction(3, 0, 1))
+            .unwrap();
+        assert_eq!(4, moved.stacks[1].len());
+        // Moving crates around within a full stack is fine.
+        assert!(moved.admit(&instruction(4, 1, 1)).is_ok());
+    }
+
+    #[test]
+    fn test_clamp_moves_the_top_crates_that_fit() {
+        let mut stacks: Stacks = "capacity 3\n1 A B C\n2 D".parse().unwrap();
+        stacks.set_overflow_policy(OverflowPolicy::Clamp);
+        assert_eq!(
+            instruction(2, 0, 1),
+            stacks.admit(&instruction(3, 0, 1)).unwrap()
+        );
+
+        let expected: Stacks = "capacity 3\n1 A\n2 D B C".parse().unwrap();
+        let mut clamped = stacks.clone();
+        clamped
+            .apply_in_place(&CrateMover9001, &instruction(3, 0, 1))
+            .unwrap();
+        clamped.set_overflow_policy(OverflowPolicy::Reject);
+        assert_eq!(expected, clamped);
+
+        let full = clamped.clone();
+        clamped.set_overflow_policy(OverflowPolicy::Clamp);
+        clamped
+            .apply_in_place(&CrateMover9000, &instruction(1, 0, 1))
+            .unwrap();
+        assert_eq!(full.stacks, clamped.stacks);
+    }
+
+    #[test]
+    fn test_capacity_lines() {
+        let stacks: Stacks = "capacity 5 for 4\n1 A\ncapacity 2\n2 B".parse().unwrap();
+        assert_eq!(4, stacks.len());
+        assert_eq!(Some(2), stacks.capacity().limit(2));
+        assert_eq!(Some(5), stacks.capacity().limit(3));
+        let reparsed: Stacks = stacks.to_string().parse().unwrap();
+        assert_eq!(stacks, reparsed);
+
+        let drawing: Stacks = "capacity 3\n    [D]\n[N] [C]\n 1   2".parse().unwrap();
+        assert_eq!(Some(3), drawing.capacity().limit(1));
+        assert_eq!("ND", drawing.tops_string().unwrap());
+
+        let error = "1 A\ncapacity 2\ncapacity 3".parse::<Stacks>().unwrap_err();
+        assert_eq!(
+            (3, ParseErrorKind::DuplicateCapacity { first_line: 2 }),
+            (error.line, error.kind)
+        );
+        let error = "capacity lots".parse::<Stacks>().unwrap_err();
+        assert_eq!(
+            (ParseErrorKind::InvalidCapacity, 9..13),
+            (error.kind, error.span)
+        );
+        let error = "capacity 3 on 1".parse::<Stacks>().unwrap_err();
+        assert_eq!(
+            (ParseErrorKind::InvalidCapacity, 0..15),
+            (error.kind, error.span)
+        );
+        let error = "capacity 3 for 0".parse::<Stacks>().unwrap_err();
+        assert_eq!(ParseErrorKind::ZeroStackId, error.kind);
+
+        // Stacks can't start with more crates than they can hold.
+        let error = "capacity 4\ncapacity 2 for 1\n1 A B C"
+            .parse::<Stacks>()
+            .unwrap_err();
+        assert_eq!(
+            (
+                2,
+                ParseErrorKind::OverCapacity {
+                    stack: 0,
+                    height: 3
+                },
+                0..16
+            ),
+            (error.line, error.kind, error.span)
+        );
+        let error = "capacity 1\n    [D]\n[N] [C]\n 1   2"
+            .parse::<Stacks>()
+            .unwrap_err();
+        assert_eq!(1, error.line);
+        assert_eq!(
+            "stack 2 starts with 2 crates, more than its capacity",
+            error.kind.to_string()
+        );
+    }
+
+    #[test]
+    fn test_errors_say_which_instruction() {
+        let input: PuzzleInput = "capacity 2\n1 A\n2 B C\n\nmove 1 from 1 to 2\n"
+            .parse()
+            .unwrap();
+        let error = input.run(&CrateMover9000).unwrap_err();
+        assert_eq!(
+            &CraneError::Overflow {
+                stack: 1,
+                capacity: 2,
+                excess: 1
+            },
+            error.cause()
+        );
+        assert_eq!(
+            "instruction 1 on line 5 (`move 1 from 1 to 2`) failed: \
+             stack 2 can only hold 2 crates, and this puts 1 too many on it",
+            error.to_string()
+        );
+
+        // Every way of running the instructions knows about the limits.
+        assert_eq!(
+            Err(error),
+            input.run_with(&CrateMover9000, StackBackend::Rope)
+        );
+        assert!(input.tops(&CrateMover9000).is_err());
+        let mut tracked = input.stacks.tracked(&CrateMover9000).unwrap();
+        assert!(tracked.apply_instructions(&input.instructions).is_err());
+
+        let instructions: CraneInstructions = "move 1 from 2 to 1".parse().unwrap();
+        assert!(input
+            .stacks
+            .apply_instructions(&CrateMover9000, &instructions)
+            .is_ok());
+    }
+}
@@ -0,0 +1,422 @@
+//! Estimating how long a list of instructions takes to carry out.
+//!
+//! A [`CostModel`] gives each `CraneInstruction` a cost, in whatever unit
+//! the model uses. [`LinearCost`] charges for each move, each crate, and
+//! each stack the crane travels past, and has figures for both of the
+//! built-in cranes; any closure from `&CraneInstruction` to `u64` works
+//! too. A [`CostReport`] sums the costs up, and shows how much traffic
+//! each stack sees and which instructions cost the most. Reports made
+//! with a crane cost the lifts it really makes, after any stack capacity
+//! or lift limit has clamped or split the instructions.
+
+use std::{
+    cmp::Reverse,
+    fmt::{self, Display},
+    slice,
+};
+
+use crate::{
+    crane::{Crane, CraneInstruction, CraneInstructions, CraneModel},
+    error::CraneError,
+    model::{Label, Stacks},
+    parse::PuzzleInput,
+    plan::Objective,
+};
+
+/// How many of the costliest instructions `CostReport`'s `Display` lists.
+const COSTLIEST_SHOWN: usize = 5;
+
+/// Something that can put a cost on an instruction.
+pub trait CostModel {
+    /// The cost of carrying out `instruction`.
+    fn cost(&self, instruction: &CraneInstruction) -> u64;
+}
+
+impl<F: Fn(&CraneInstruction) -> u64> CostModel for F {
+    fn cost(&self, instruction: &CraneInstruction) -> u64 {
+        self(instruction)
+    }
+}
+
+// The planner searches with its objective's cost model, so plans can be
+// reported on with the same cost they were searched for with.
+impl CostModel for Objective {
+    fn cost(&self, instruction: &CraneInstruction) -> u64 {
+        match self {
+            Self::Instructions => 1,
+            Self::CratesLifted => instruction.num_to_move as u64,
+        }
+    }
+}
+
+/// A cost that's a fixed amount per move, plus an amount per crate, plus
+/// an amount for each stack the crane passes on each round trip between
+/// the two stacks of a move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LinearCost {
+    pub per_move: u64,
+    pub per_crate: u64,
+    /// For each stack between the source and the destination, charged
+    /// twice on each trip: once there and once back.
+    pub per_stack: u64,
+    /// Whether the crane makes a trip for every crate, like the
+    /// `CrateMover9000`, rather than one for the whole group.
+    pub one_at_a_time: bool,
+}
+
+impl LinearCost {
+    /// Rough figures in seconds for the `CrateMover9000`.
+    pub const CRATE_MOVER_9000: Self = Self {
+        per_move: 5,
+        per_crate: 2,
+        per_stack: 1,
+        one_at_a_time: true,
+    };
+
+    /// Rough figures in seconds for the `CrateMover9001`, which is slower
+    /// to load but only makes one trip.
+    pub const CRATE_MOVER_9001: Self = Self {
+        per_move: 5,
+        per_crate: 3,
+        per_stack: 1,
+        one_at_a_time: false,
+    };
+
+    /// The figures for one of the built-in cranes.
+    #[must_use]
+    pub const fn for_crane(crane: CraneModel) -> Self {
+        match crane {
+            CraneModel::CrateMover9000 => Self::CRATE_MOVER_9000,
+            CraneModel::CrateMover9001 => Self::CRATE_MOVER_9001,
+        }
+    }
+}
+
+impl CostModel for LinearCost {
+    fn cost(&self, instruction: &CraneInstruction) -> u64 {
+        let crates = instruction.num_to_move as u64;
+        let distance = instruction.from_stack.abs_diff(instruction.to_stack) as u64;
+        // Moving no crates doesn't need a trip at all.
+        let trips = if self.one_at_a_time {
+            crates
+        } else {
+            crates.min(1)
+        };
+        self.per_move + self.per_crate * crates + self.per_stack * 2 * distance * trips
+    }
+}
+
+/// The cost of one instruction in a `CostReport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionCost {
+    /// The position of the instruction in the list, starting at 0.
+    pub index: usize,
+    /// The instruction as it's written in the list.
+    pub instruction: CraneInstruction,
+    /// The total cost of the lifts made for the instruction.
+    pub cost: u64,
+}
+
+/// How many crates were taken off and put on a stack.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StackTraffic {
+    pub crates_out: usize,
+    pub crates_in: usize,
+}
+
+/// The costs of a list of instructions, created by
+/// [`Stacks::cost_report`], [`PuzzleInput::cost_report`], or
+/// [`CraneInstructions::cost_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostReport {
+    pub total: u64,
+    /// Every instruction's cost, in order.
+    pub costs: Vec<InstructionCost>,
+    /// Indexed by stack.
+    pub traffic: Vec<StackTraffic>,
+}
+
+impl CostReport {
+    fn new(num_stacks: usize) -> Self {
+        Self {
+            total: 0,
+            costs: Vec::new(),
+            traffic: vec![StackTraffic::default(); num_stacks],
+        }
+    }
+
+    /// Add the instruction with this index, carried out as `lifts`.
+    fn add<M: CostModel + ?Sized>(
+        &mut self,
+        model: &M,
+        index: usize,
+        instruction: &CraneInstruction,
+        lifts: &[CraneInstruction],
+    ) {
+        let mut cost = 0;
+        for lift in lifts {
+            cost += model.cost(lift);
+            let needed = lift.from_stack.max(lift.to_stack) + 1;
+            if self.traffic.len() < needed {
+                self.traffic.resize(needed, StackTraffic::default());
+            }
+            self.traffic[lift.from_stack].crates_out += lift.num_to_move;
+            self.traffic[lift.to_stack].crates_in += lift.num_to_move;
+        }
+        self.total += cost;
+        self.costs.push(InstructionCost {
+            index,
+            instruction: *instruction,
+            cost,
+        });
+    }
+
+    /// The `n` costliest instructions, most expensive first. Instructions
+    /// that cost the same are in the order they appear.
+    #[must_use]
+    pub fn costliest(&self, n: usize) -> Vec<&InstructionCost> {
+        let mut costs: Vec<&InstructionCost> = self.costs.iter().collect();
+        costs.sort_by_key(|cost| Reverse(cost.cost));
+        costs.truncate(n);
+        costs
+    }
+}
+
+// For example:
+//
+// total cost 15 for 1 instructions
+// stack 1: 2 crates out, 0 in
+// ...
+// costliest instructions:
+//   1 (move 2 from 1 to 3): 15
+impl Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "total cost {} for {} instructions",
+            self.total,
+            self.costs.len()
+        )?;
+        for (stack, traffic) in self.traffic.iter().enumerate() {
+            writeln!(
+                f,
+                "stack {}: {} crates out, {} in",
+                stack + 1,
+                traffic.crates_out,
+                traffic.crates_in
+            )?;
+        }
+        if !self.costs.is_empty() {
+            writeln!(f, "costliest instructions:")?;
+        }
+        for cost in self.costliest(COSTLIEST_SHOWN) {
+            writeln!(
+                f,
+                "  {} ({}): {}",
+                cost.index + 1,
+                cost.instruction,
+                cost.cost
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl CraneInstructions {
+    /// The total cost of these instructions under `model`.
+    pub fn cost<M: CostModel + ?Sized>(&self, model: &M) -> u64 {
+        self.iter().map(|instruction| model.cost(instruction)).sum()
+    }
+
+    /// A report on the cost of these instructions under `model`, with
+    /// the traffic of at least `num_stacks` stacks, or more if the
+    /// instructions use more.
+    ///
+    /// This costs the instructions exactly as they're written. Use
+    /// `Stacks::cost_report` to cost the lifts a crane really makes,
+    /// which differ if a stack capacity clamps a move or a lift limit
+    /// splits one.
+    #[must_use]
+    pub fn cost_report<M: CostModel + ?Sized>(&self, model: &M, num_stacks: usize) -> CostReport {
+        let mut report = CostReport::new(num_stacks);
+        for (index, instruction) in self.iter().enumerate() {
+            report.add(model, index, instruction, slice::from_ref(instruction));
+        }
+        report
+    }
+}
+
+impl<L: Label + Clone> Stacks<L> {
+    /// A report on the cost under `model` of the lifts `crane` makes to
+    /// carry out `instructions` on these stacks. Each instruction costs
+    /// as much as all of its lifts, and the traffic includes any buffer
+    /// stack a split lift goes through.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `apply_instructions` would.
+    pub fn cost_report<C: Crane + ?Sized, M: CostModel + ?Sized>(
+        &self,
+        crane: &C,
+        instructions: &CraneInstructions,
+        model: &M,
+    ) -> Result<CostReport, CraneError<L>> {
+        let mut report = CostReport::new(self.len());
+        self.for_each_lifts(crane, instructions, |index, instruction, lifts| {
+            report.add(model, index, instruction, lifts);
+        })?;
+        Ok(report)
+    }
+}
+
+impl<L: Label + Clone> PuzzleInput<L> {
+    /// A report on the cost of the instructions with `crane` under
+    /// `model`, like `Stacks::cost_report`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `run`.
+    pub fn cost_report<C: Crane + ?Sized, M: CostModel + ?Sized>(
+        &self,
+        crane: &C,
+        model: &M,
+    ) -> Result<CostReport, CraneError<L>> {
+        self.stacks
+            .cost_report(crane, &self.instructions, model)
+            .map_err(|error| self.locate_error(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crane::{CrateMover9000, CrateMover9001},
+        lift::{LiftLimit, LiftPolicy, Limited},
+    };
+
+    const EXAMPLE: &str = "1 Z N\n2 M C D\n3 P\n\n\
+                           move 1 from 2 to 1\n\
+                           move 3 from 1 to 3\n\
+                           move 2 from 2 to 1\n\
+                           move 1 from 1 to 2\n";
+
+    fn instruction(num_to_move: usize, from_stack: usize, to_stack: usize) -> CraneInstruction {
+        CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        }
+    }
+
+    #[test]
+    fn test_linear_cost() {
+        let far = instruction(3, 0, 2);
+        // 5 per move + 2 * 3 crates + 1 * 2 stacks there and back * 3 trips
+        assert_eq!(23, LinearCost::CRATE_MOVER_9000.cost(&far));
+        // 5 per move + 3 * 3 crates + 1 * 2 stacks there and back * 1 trip
+        assert_eq!(18, LinearCost::CRATE_MOVER_9001.cost(&far));
+        assert_eq!(
+            LinearCost::CRATE_MOVER_9001,
+            LinearCost::for_crane(CraneModel::CrateMover9001)
+        );
+
+        let nothing = instruction(0, 0, 2);
+        assert_eq!(5, LinearCost::CRATE_MOVER_9000.cost(&nothing));
+        assert_eq!(5, LinearCost::CRATE_MOVER_9001.cost(&nothing));
+        assert_eq!(0, Objective::CratesLifted.cost(&nothing));
+    }
+
+    #[test]
+    fn test_report() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let report = input
+            .cost_report(&CrateMover9000, &Objective::CratesLifted)
+            .unwrap();
+        assert_eq!(7, report.total);
+        assert_eq!(
+            input.instructions.cost(&Objective::CratesLifted),
+            report.total
+        );
+        assert_eq!(
+            vec![
+                StackTraffic {
+                    crates_out: 4,
+                    crates_in: 3
+                },
+                StackTraffic {
+                    crates_out: 3,
+                    crates_in: 1
+                },
+                StackTraffic {
+                    crates_out: 0,
+                    crates_in: 3
+                },
+            ],
+            report.traffic
+        );
+        let costliest: Vec<usize> = report.costliest(3).iter().map(|c| c.index).collect();
+        assert_eq!(vec![1, 2, 0], costliest);
+
+        let expected = "total cost 7 for 4 instructions\n\
+                        stack 1: 4 crates out, 3 in\n\
+                        stack 2: 3 crates out, 1 in\n\
+                        stack 3: 0 crates out, 3 in\n\
+                        costliest instructions:\n  \
+                          2 (move 3 from 1 to 3): 3\n  \
+                          3 (move 2 from 2 to 1): 2\n  \
+                          1 (move 1 from 2 to 1): 1\n  \
+                          4 (move 1 from 1 to 2): 1\n";
+        assert_eq!(expected, report.to_string());
+    }
+
+    #[test]
+    fn test_custom_models() {
+        let instructions: CraneInstructions =
+            "move 1 from 1 to 4\nmove 5 from 2 to 2".parse().unwrap();
+        let distance = |instruction: &CraneInstruction| {
+            instruction.from_stack.abs_diff(instruction.to_stack) as u64
+        };
+        assert_eq!(3, instructions.cost(&distance));
+
+        // Stacks the instructions use are added to the traffic.
+        let report = instructions.cost_report(&distance, 2);
+        assert_eq!(4, report.traffic.len());
+        assert_eq!(5, report.traffic[1].crates_out);
+        assert_eq!(5, report.traffic[1].crates_in);
+
+        let dynamic: &dyn CostModel = &LinearCost::CRATE_MOVER_9000;
+        assert_eq!(
+            instructions.cost(&LinearCost::CRATE_MOVER_9000),
+            instructions.cost(dynamic)
+        );
+    }
+
+    #[test]
+    fn test_report_costs_split_lifts() {
+        let input: PuzzleInput = "1 A B C D\n2\n3\n\nmove 4 from 1 to 2\n".parse().unwrap();
+        let model = LinearCost::CRATE_MOVER_9001;
+        let unlimited = input.cost_report(&CrateMover9001, &model).unwrap();
+        assert_eq!(input.instructions.cost_report(&model, 3), unlimited);
+
+        // Two lifts of 2 onto stack 3, and two from there onto stack 2.
+        let limit = LiftLimit::new().max_crates(2).policy(LiftPolicy::Split);
+        let crane = Limited::new(CrateMover9001, limit);
+        let split = input.cost_report(&crane, &model).unwrap();
+        assert_eq!(1, split.costs.len());
+        assert_eq!(2 * (5 + 6 + 4) + 2 * (5 + 6 + 2), split.total);
+        assert_eq!(split.total, split.costs[0].cost);
+        assert_eq!(4, split.traffic[2].crates_in);
+
+        let error = input
+            .cost_report(
+                &Limited::new(CrateMover9001, LiftLimit::new().max_crates(2)),
+                &model,
+            )
+            .unwrap_err();
+        let CraneError::Instruction(failure) = error else {
+            panic!("expected an instruction failure");
+        };
+        assert_eq!(Some(5), failure.line);
+    }
+}
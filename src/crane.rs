@@ -0,0 +1,659 @@
+//! Crane instructions and how the crane applies them to a set of stacks.
+//!
+//! How a group of crates lands on the destination stack depends on the
+//! crane, so that behaviour lives behind the [`Crane`] trait. The two
+//! cranes from the puzzle are [`CrateMover9000`] and [`CrateMover9001`];
+//! [`CraneModel`] picks between them at runtime.
+
+use std::fmt::{self, Display};
+
+use crate::{
+    error::{CraneError, InstructionFailure},
+    lift::LiftLimit,
+    model::{Label, Stack, Stacks},
+};
+
+/// A crane that can move crates from one stack to another.
+///
+/// Implementing this is all it takes to simulate a new kind of crane;
+/// `Stacks::apply_instructions` is generic over it.
+pub trait Crane {
+    /// Move the top `n` crates of `from` onto `to`.
+    ///
+    /// Callers guarantee that `from` holds at least `n` crates, so
+    /// implementations don't need to check that again.
+    fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize);
+
+    /// An instruction that exactly undoes `instruction` with this crane,
+    /// if there is one.
+    ///
+    /// Not every crane's moves can be undone by another move, so the
+    /// default is `None`.
+    fn inverse(&self, _instruction: &CraneInstruction) -> Option<CraneInstruction> {
+        None
+    }
+
+    /// How this crane puts down a group of crates, if it's always one of
+    /// the simple ways in [`Landing`].
+    ///
+    /// Storage that doesn't keep crates in a `Stack`, like `RopeStacks`,
+    /// uses this to move whole groups without calling `transfer`. The
+    /// default is `None`, which means only `transfer` describes the crane.
+    /// If this returns a `Landing`, it must agree with `transfer`.
+    fn landing(&self) -> Option<Landing> {
+        None
+    }
+
+    /// Whether `instruction` always leaves the stacks as they were.
+    ///
+    /// This and `merge` are what the plan optimizer knows about a crane.
+    /// The default is `false`, which is always safe.
+    fn is_no_op(&self, _instruction: &CraneInstruction) -> bool {
+        false
+    }
+
+    /// A single instruction that does the same as `first` followed by
+    /// `second` with this crane, if there is one. The default is `None`.
+    fn merge(
+        &self,
+        _first: &CraneInstruction,
+        _second: &CraneInstruction,
+    ) -> Option<CraneInstruction> {
+        None
+    }
+
+    /// How much this crane can lift at once, if there's a limit. The
+    /// default is `None`; see `Limited` for cranes that have one.
+    fn lift_limit(&self) -> Option<&LiftLimit> {
+        None
+    }
+}
+
+/// The order a moved group of crates ends up in on the destination stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Landing {
+    /// The same order as on the source stack.
+    InOrder,
+    /// The top crate of the group ends up at the bottom.
+    Reversed,
+}
+
+/// Moves crates one at a time, so a moved group ends up reversed (part 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize) {
+        to.extend(from.take_top(n).into_iter().rev());
+    }
+
+    fn landing(&self) -> Option<Landing> {
+        Some(Landing::Reversed)
+    }
+
+    // Moving the crates back one at a time reverses them again.
+    fn inverse(&self, instruction: &CraneInstruction) -> Option<CraneInstruction> {
+        Some(instruction.swapped())
+    }
+
+    // Moving a crate or none onto the same stack doesn't change anything,
+    // but moving more reverses them.
+    fn is_no_op(&self, instruction: &CraneInstruction) -> bool {
+        instruction.num_to_move == 0
+            || (instruction.num_to_move == 1 && instruction.from_stack == instruction.to_stack)
+    }
+
+    fn merge(
+        &self,
+        first: &CraneInstruction,
+        second: &CraneInstruction,
+    ) -> Option<CraneInstruction> {
+        // Moving crates one at a time between the same two stacks is the
+        // same however the moves are split up.
+        if first.from_stack == second.from_stack
+            && first.to_stack == second.to_stack
+            && first.from_stack != first.to_stack
+        {
+            return Some(CraneInstruction {
+                num_to_move: first.num_to_move + second.num_to_move,
+                ..*first
+            });
+        }
+        merge_chain(first, second).filter(|merged| merged.num_to_move == 1)
+    }
+}
+
+/// Picks up the whole group at once, preserving its order (part 2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize) {
+        to.extend(from.take_top(n));
+    }
+
+    fn landing(&self) -> Option<Landing> {
+        Some(Landing::InOrder)
+    }
+
+    fn inverse(&self, instruction: &CraneInstruction) -> Option<CraneInstruction> {
+        Some(instruction.swapped())
+    }
+
+    fn is_no_op(&self, instruction: &CraneInstruction) -> bool {
+        instruction.num_to_move == 0 || instruction.from_stack == instruction.to_stack
+    }
+
+    // Unlike the 9000, two moves between the same stacks can't be merged:
+    // the second group lands on top of the first, where it started out
+    // underneath it.
+    fn merge(
+        &self,
+        first: &CraneInstruction,
+        second: &CraneInstruction,
+    ) -> Option<CraneInstruction> {
+        merge_chain(first, second)
+    }
+}
+
+/// Moving a group from `A` to `B` and then the same group on to `C` is the
+/// same as moving it straight to `C`, as long as the order of the group
+/// doesn't change. That's true of any crane for a single crate, and of
+/// the 9001 for any number.
+fn merge_chain(first: &CraneInstruction, second: &CraneInstruction) -> Option<CraneInstruction> {
+    let same_group = first.num_to_move == second.num_to_move;
+    (same_group && first.to_stack == second.from_stack).then_some(CraneInstruction {
+        num_to_move: first.num_to_move,
+        from_stack: first.from_stack,
+        to_stack: second.to_stack,
+    })
+}
+
+/// One of the built-in cranes, for when the choice is only known at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CraneModel {
+    #[default]
+    CrateMover9000,
+    CrateMover9001,
+}
+
+impl Crane for CraneModel {
+    fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize) {
+        match self {
+            Self::CrateMover9000 => CrateMover9000.transfer(from, to, n),
+            Self::CrateMover9001 => CrateMover9001.transfer(from, to, n),
+        }
+    }
+
+    fn landing(&self) -> Option<Landing> {
+        match self {
+            Self::CrateMover9000 => CrateMover9000.landing(),
+            Self::CrateMover9001 => CrateMover9001.landing(),
+        }
+    }
+
+    fn inverse(&self, instruction: &CraneInstruction) -> Option<CraneInstruction> {
+        match self {
+            Self::CrateMover9000 => CrateMover9000.inverse(instruction),
+            Self::CrateMover9001 => CrateMover9001.inverse(instruction),
+        }
+    }
+
+    fn is_no_op(&self, instruction: &CraneInstruction) -> bool {
+        match self {
+            Self::CrateMover9000 => CrateMover9000.is_no_op(instruction),
+            Self::CrateMover9001 => CrateMover9001.is_no_op(instruction),
+        }
+    }
+
+    fn merge(
+        &self,
+        first: &CraneInstruction,
+        second: &CraneInstruction,
+    ) -> Option<CraneInstruction> {
+        match self {
+            Self::CrateMover9000 => CrateMover9000.merge(first, second),
+            Self::CrateMover9001 => CrateMover9001.merge(first, second),
+        }
+    }
+}
+
+/// A single `move N from A to B` instruction. The stack fields are
+/// zero-based indices into `Stacks`, even though the input numbers
+/// the stacks starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CraneInstruction {
+    pub num_to_move: usize,
+    pub from_stack: usize,
+    pub to_stack: usize,
+}
+
+impl CraneInstruction {
+    /// The same number of crates moved the other way, from `to_stack`
+    /// back to `from_stack`.
+    #[must_use]
+    pub const fn swapped(&self) -> Self {
+        Self {
+            num_to_move: self.num_to_move,
+            from_stack: self.to_stack,
+            to_stack: self.from_stack,
+        }
+    }
+
+    /// Check this instruction against `num_stacks` stacks, where `height`
+    /// gives the number of crates on a stack that exists.
+    pub(crate) fn check<L>(
+        &self,
+        num_stacks: usize,
+        height: impl FnOnce(usize) -> usize,
+    ) -> Result<(), CraneError<L>> {
+        // Throw error if the stack moving from or moving to does not exist
+        for stack in [self.from_stack, self.to_stack] {
+            if stack >= num_stacks {
+                return Err(CraneError::InvalidStack { stack, num_stacks });
+            }
+        }
+
+        let available = height(self.from_stack);
+        if available < self.num_to_move {
+            return Err(CraneError::InvalidMove {
+                from_stack: self.from_stack,
+                requested: self.num_to_move,
+                available,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Instructions are displayed the way they're written in the input,
+// with the stacks numbered from 1.
+impl Display for CraneInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.num_to_move,
+            self.from_stack + 1,
+            self.to_stack + 1
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CraneInstructions {
+    pub(crate) instructions: Vec<CraneInstruction>,
+}
+
+impl CraneInstructions {
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &CraneInstruction> {
+        self.instructions.iter()
+    }
+
+    /// The instructions that undo these ones with `crane`: the inverse of
+    /// each instruction, last one first. Applying these instructions and
+    /// then their inverse leaves the stacks as they started, as long as
+    /// every instruction moved all the crates it asked for. That isn't so
+    /// when a stack capacity clamps a move under `OverflowPolicy::Clamp`,
+    /// and a crane with a `LiftLimit` may not be able to split an inverse
+    /// lift; `History` undoes moves like these correctly.
+    ///
+    /// Returns `None` if the crane can't undo one of the instructions.
+    #[must_use]
+    pub fn inverse<C: Crane + ?Sized>(&self, crane: &C) -> Option<Self> {
+        let instructions = self
+            .instructions
+            .iter()
+            .rev()
+            .map(|instruction| crane.inverse(instruction))
+            .collect::<Option<_>>()?;
+        Some(Self { instructions })
+    }
+}
+
+impl FromIterator<CraneInstruction> for CraneInstructions {
+    fn from_iter<T: IntoIterator<Item = CraneInstruction>>(iter: T) -> Self {
+        Self {
+            instructions: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<L: Label> Stacks<L> {
+    /// Apply a single instruction to the set of stacks in `self`.
+    /// Return the new set of stacks, or a `CraneError` if the instruction
+    /// is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CraneError::InvalidStack` if either stack doesn't exist, and
+    /// `CraneError::InvalidMove` if there aren't enough crates to move.
+    pub fn apply_instruction<C: Crane + ?Sized>(
+        mut self,
+        crane: &C,
+        instruction: &CraneInstruction,
+    ) -> Result<Self, CraneError<L>> {
+        self.apply_in_place(crane, instruction)?;
+        Ok(self)
+    }
+
+    /// Check that an instruction can be applied to these stacks, without
+    /// applying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `apply_instruction` would.
+    pub fn check_instruction(&self, instruction: &CraneInstruction) -> Result<(), CraneError<L>> {
+        self.admit(instruction).map(|_| ())
+    }
+
+    /// Apply a single instruction to `self` in place. If the instruction
+    /// is invalid the stacks are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `apply_instruction`.
+    pub fn apply_in_place<C: Crane + ?Sized>(
+        &mut self,
+        crane: &C,
+        instruction: &CraneInstruction,
+    ) -> Result<(), CraneError<L>> {
+        for lift in self.plan_lifts(crane, instruction)?.as_slice() {
+            self.lift(crane, lift);
+        }
+        Ok(())
+    }
+
+    /// Make a single lift that's already been checked.
+    pub(crate) fn lift<C: Crane + ?Sized>(&mut self, crane: &C, instruction: &CraneInstruction) {
+        if instruction.from_stack == instruction.to_stack {
+            // A crane can't hold the same stack as both source and target, so
+            // land the crates on a scratch stack and put them back from there.
+            let stack = &mut self.stacks[instruction.from_stack];
+            let mut landed = Stack::default();
+            crane.transfer(stack, &mut landed, instruction.num_to_move);
+            // The crane might not have landed all of them.
+            stack.extend(landed.take_top(landed.len()));
+        } else {
+            let (from, to) = self.pair_mut(instruction.from_stack, instruction.to_stack);
+            crane.transfer(from, to, instruction.num_to_move);
+        }
+    }
+
+    /// Mutable references to two different stacks at once.
+    fn pair_mut(&mut self, first: usize, second: usize) -> (&mut Stack<L>, &mut Stack<L>) {
+        debug_assert_ne!(first, second);
+        if first < second {
+            let (low, high) = self.stacks.split_at_mut(second);
+            (&mut low[first], &mut high[0])
+        } else {
+            let (low, high) = self.stacks.split_at_mut(first);
+            (&mut high[0], &mut low[second])
+        }
+    }
+
+    /// Perform each of these instructions in order on the set of stacks
+    /// in `self`. Return the new set of stacks, or a `CraneError` if
+    /// any of the instructions are invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CraneError::Instruction` for the first instruction that
+    /// fails, saying which instruction it was and holding the stacks as
+    /// they were just before it.
+    pub fn apply_instructions<C: Crane + ?Sized>(
+        mut self,
+        crane: &C,
+        instructions: &CraneInstructions,
+    ) -> Result<Self, CraneError<L>> {
+        // for each instruction in the instructions, apply the instruction to the stacks
+        for (index, instruction) in instructions.iter().enumerate() {
+            if let Err(cause) = self.apply_in_place(crane, instruction) {
+                // `apply_in_place` leaves the stacks alone when it fails,
+                // so `self` is still the state before this instruction.
+                return Err(CraneError::Instruction(Box::new(InstructionFailure {
+                    index,
+                    line: None,
+                    instruction: *instruction,
+                    before: self,
+                    cause,
+                })));
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{parse::PuzzleInput, test_util::TopOnly};
+
+    fn abc_def_ghi() -> Stacks {
+        Stacks::from(vec![
+            Stack::from(vec!['A', 'B', 'C']),
+            Stack::from(vec!['D', 'E', 'F']),
+            Stack::from(vec!['G', 'H', 'I']),
+        ])
+    }
+
+    const MOVE_2_FROM_0_TO_1: CraneInstruction = CraneInstruction {
+        num_to_move: 2,
+        from_stack: 0,
+        to_stack: 1,
+    };
+
+    // Test that the instruction `move 2 from 0 to 1` works as expected with non-empty
+    // stacks.
+    #[test]
+    fn test_apply_instruction() {
+        let new_stacks = abc_def_ghi()
+            .apply_instruction(&CrateMover9000, &MOVE_2_FROM_0_TO_1)
+            .expect("Failed to apply instruction");
+
+        assert_eq!(new_stacks.stacks[0], vec!['A']);
+        assert_eq!(new_stacks.stacks[1], vec!['D', 'E', 'F', 'C', 'B']);
+    }
+
+    #[test]
+    fn test_apply_instruction_9001_preserves_order() {
+        let new_stacks = abc_def_ghi()
+            .apply_instruction(&CrateMover9001, &MOVE_2_FROM_0_TO_1)
+            .expect("Failed to apply instruction");
+
+        assert_eq!(new_stacks.stacks[0], vec!['A']);
+        assert_eq!(new_stacks.stacks[1], vec!['D', 'E', 'F', 'B', 'C']);
+    }
+
+    #[test]
+    fn test_apply_instruction_too_many_crates() {
+        let instruction = CraneInstruction {
+            num_to_move: 4,
+            from_stack: 0,
+            to_stack: 1,
+        };
+        let result = abc_def_ghi().apply_instruction(&CrateMover9000, &instruction);
+        assert_eq!(
+            Err(CraneError::InvalidMove {
+                from_stack: 0,
+                requested: 4,
+                available: 3,
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn test_apply_instruction_invalid_stack() {
+        let instruction = CraneInstruction {
+            num_to_move: 1,
+            from_stack: 0,
+            to_stack: 3,
+        };
+        let result = abc_def_ghi().apply_instruction(&CrateMover9000, &instruction);
+        assert_eq!(
+            Err(CraneError::InvalidStack {
+                stack: 3,
+                num_stacks: 3,
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn test_apply_instruction_same_stack() {
+        let instruction = CraneInstruction {
+            num_to_move: 2,
+            from_stack: 0,
+            to_stack: 0,
+        };
+        let reversed = abc_def_ghi()
+            .apply_instruction(&CrateMover9000, &instruction)
+            .unwrap();
+        assert_eq!(reversed.stacks[0], vec!['A', 'C', 'B']);
+        let unchanged = abc_def_ghi()
+            .apply_instruction(&CrateMover9001, &instruction)
+            .unwrap();
+        assert_eq!(unchanged.stacks[0], vec!['A', 'B', 'C']);
+    }
+
+    #[test]
+    fn test_crane_model_matches_builtin_cranes() {
+        let ours = abc_def_ghi()
+            .apply_instruction(&CraneModel::CrateMover9001, &MOVE_2_FROM_0_TO_1)
+            .unwrap();
+        let theirs = abc_def_ghi()
+            .apply_instruction(&CrateMover9001, &MOVE_2_FROM_0_TO_1)
+            .unwrap();
+        assert_eq!(ours, theirs);
+    }
+
+    #[test]
+    fn test_custom_crane() {
+        let new_stacks = abc_def_ghi()
+            .apply_instruction(&TopOnly, &MOVE_2_FROM_0_TO_1)
+            .unwrap();
+        assert_eq!(new_stacks.stacks[0], vec!['A', 'B']);
+        assert_eq!(new_stacks.stacks[1], vec!['D', 'E', 'F', 'C']);
+
+        let same_stack = CraneInstruction {
+            num_to_move: 2,
+            from_stack: 0,
+            to_stack: 0,
+        };
+        let new_stacks = abc_def_ghi()
+            .apply_instruction(&TopOnly, &same_stack)
+            .unwrap();
+        assert_eq!(abc_def_ghi(), new_stacks);
+    }
+
+    #[test]
+    fn test_inverse_restores_initial_stacks() {
+        let contents = fs::read_to_string("input.txt").unwrap();
+        let input: PuzzleInput = contents.parse().unwrap();
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let inverse = input.instructions.inverse(&crane).unwrap();
+            assert_eq!(input.instructions.len(), inverse.len());
+            let restored = input
+                .run(&crane)
+                .unwrap()
+                .apply_instructions(&crane, &inverse)
+                .unwrap();
+            assert_eq!(input.stacks, restored);
+        }
+    }
+
+    #[test]
+    fn test_inverse_same_stack_move() {
+        let instructions = CraneInstructions {
+            instructions: vec![
+                MOVE_2_FROM_0_TO_1,
+                CraneInstruction {
+                    num_to_move: 3,
+                    from_stack: 1,
+                    to_stack: 1,
+                },
+            ],
+        };
+        let inverse = instructions.inverse(&CrateMover9000).unwrap();
+        let restored = abc_def_ghi()
+            .apply_instructions(&CrateMover9000, &instructions)
+            .and_then(|stacks| stacks.apply_instructions(&CrateMover9000, &inverse))
+            .unwrap();
+        assert_eq!(abc_def_ghi(), restored);
+    }
+
+    #[test]
+    fn test_inverse_needs_invertible_crane() {
+        let instructions = CraneInstructions {
+            instructions: vec![MOVE_2_FROM_0_TO_1],
+        };
+        assert_eq!(None, instructions.inverse(&TopOnly));
+    }
+
+    #[test]
+    fn test_apply_instructions_reports_failing_step() {
+        let instructions: CraneInstructions = "move 2 from 1 to 2\n\
+                                               move 3 from 3 to 1\n\
+                                               move 5 from 1 to 3"
+            .parse()
+            .unwrap();
+        let error = abc_def_ghi()
+            .apply_instructions(&CrateMover9001, &instructions)
+            .unwrap_err();
+        let CraneError::Instruction(failure) = &error else {
+            panic!("expected an instruction failure, got {error:?}");
+        };
+        assert_eq!(2, failure.index);
+        assert_eq!(instructions.instructions[2], failure.instruction);
+        assert_eq!(failure.before.stacks[0], vec!['A', 'G', 'H', 'I']);
+        assert_eq!(
+            &CraneError::InvalidMove {
+                from_stack: 0,
+                requested: 5,
+                available: 4,
+            },
+            error.cause()
+        );
+        assert_eq!(
+            "instruction 3 on line 7 (`move 5 from 1 to 3`) failed: \
+             tried to move 5 crates from stack 1, which only has 4",
+            error.on_line(7).to_string()
+        );
+    }
+
+    fn run_input_file(crane: &impl Crane) -> String {
+        let contents = fs::read_to_string("input.txt").expect("Failed to open file 'input.txt'");
+        let input: PuzzleInput = contents.parse().expect("Failed to parse input");
+        input
+            .run(crane)
+            .expect("Applying an instruction set failed")
+            .tops_string()
+            .expect("Tried to take the top of an empty stack")
+    }
+
+    // These check the answers to both parts of the puzzle for `input.txt`.
+    #[test]
+    fn test_part_1() {
+        assert_eq!("SBPQRSCDF", run_input_file(&CrateMover9000));
+    }
+
+    #[test]
+    fn test_part_2() {
+        // This is different from part 1 because the 9001 can grab multiple crates at once.
+        assert_eq!("RGLVRCQSB", run_input_file(&CrateMover9001));
+    }
+}
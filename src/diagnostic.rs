@@ -0,0 +1,266 @@
+//! Compiler-style rendering of errors in an input file.
+//!
+//! A [`Diagnostic`] shows where in the input something went wrong, with
+//! the source line and a caret under the offending text:
+//!
+//! ```text
+//! error: instruction uses a stack that doesn't exist
+//!   --> input.txt:12:18
+//!    |
+//! 12 | move 1 from 2 to 10
+//!    |                  ^^
+//!    = hint: stack 10 does not exist; configuration defines 9 stacks
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use crate::{
+    error::{CraneError, ParseError, ParseErrorKind},
+    lift::LiftPolicy,
+};
+
+/// An error message tied to a location in an input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub file_name: String,
+    /// The line the problem is on, starting at 1.
+    pub line: usize,
+    /// The columns to underline, counted in characters from 0.
+    pub span: Range<usize>,
+    /// The text of the line the problem is on.
+    pub source_line: String,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Describe a `ParseError` from parsing `source`, which was read from `file_name`.
+    ///
+    /// The error's line numbers need to be lines of `source`, so this is
+    /// usually used with errors from parsing a whole `PuzzleInput`.
+    #[must_use]
+    pub fn from_parse_error(error: &ParseError, file_name: &str, source: &str) -> Self {
+        Self {
+            message: error.kind.to_string(),
+            file_name: file_name.to_string(),
+            line: error.line,
+            span: error.span.clone(),
+            source_line: source_line(source, error.line),
+            hint: parse_hint(error),
+        }
+    }
+
+    /// Describe a `CraneError` from running the instructions in `source`.
+    ///
+    /// Only errors that know which input line they came from (like those
+    /// from `PuzzleInput::run`) can be shown this way; for any others this
+    /// returns `None`.
+    #[must_use]
+    pub fn from_crane_error(error: &CraneError, file_name: &str, source: &str) -> Option<Self> {
+        let CraneError::Instruction(failure) = error else {
+            return None;
+        };
+        let line = failure.line?;
+        let source_line = source_line(source, line);
+        // Underline the whole instruction.
+        let start = source_line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        let span = start..start + source_line.trim().chars().count();
+        Some(Self {
+            message: failure.cause.to_string(),
+            file_name: file_name.to_string(),
+            line,
+            span,
+            source_line,
+            hint: crane_hint(&failure.cause),
+        })
+    }
+}
+
+fn source_line(source: &str, line: usize) -> String {
+    source
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_hint(error: &ParseError) -> Option<String> {
+    let text = &error.text;
+    let hint = match &error.kind {
+        ParseErrorKind::MissingStackId => {
+            "each stack line starts with its stack number, like `1 Z N`".to_string()
+        }
+        ParseErrorKind::ZeroStackId | ParseErrorKind::NegativeStackId => {
+            "stacks are numbered starting from 1".to_string()
+        }
+        ParseErrorKind::DuplicateStackId { first_line } => {
+            format!("stack {text} was first defined on line {first_line}")
+        }
+        ParseErrorKind::StackIdOutOfRange { max } => {
+            format!("at most {max} stacks are supported")
+        }
+        ParseErrorKind::UnknownStack { num_stacks } => {
+            format!("stack {text} does not exist; configuration defines {num_stacks} stacks")
+        }
+        ParseErrorKind::IllegalCrateLabel { expected } => {
+            format!("each crate is {expected}, separated from the next by spaces")
+        }
+        ParseErrorKind::InvalidCapacity => {
+            "capacities look like `capacity 6` for every stack or `capacity 4 for 2` for one"
+                .to_string()
+        }
+        ParseErrorKind::DuplicateCapacity { first_line } => {
+            format!("the capacity set on line {first_line} would be replaced")
+        }
+        ParseErrorKind::OverCapacity { stack, height } => format!(
+            "the capacity of stack {} must be at least {height}",
+            stack + 1
+        ),
+        ParseErrorKind::MisalignedCrate => {
+            "crates must sit directly above a stack number in the footer".to_string()
+        }
+        ParseErrorKind::UnexpectedToken { .. } | ParseErrorKind::MissingToken { .. } => {
+            "instructions look like `move 1 from 2 to 3`".to_string()
+        }
+        ParseErrorKind::InvalidInstruction => {
+            "each instruction needs exactly three numbers".to_string()
+        }
+        ParseErrorKind::MissingBlankLine => {
+            "the stack configuration and the instructions are separated by a blank line".to_string()
+        }
+        ParseErrorKind::StackIdParseError(_) | ParseErrorKind::CrateCountParseError(_) => {
+            return None;
+        }
+    };
+    Some(hint)
+}
+
+fn crane_hint(cause: &CraneError) -> Option<String> {
+    match cause {
+        CraneError::InvalidStack { stack, num_stacks } => Some(format!(
+            "stack {} does not exist; configuration defines {num_stacks} stacks",
+            stack + 1
+        )),
+        CraneError::InvalidMove {
+            from_stack,
+            requested,
+            available,
+        } => Some(format!(
+            "stack {} has {available} crates at this point, but {requested} were requested",
+            from_stack + 1
+        )),
+        CraneError::Overflow {
+            stack, capacity, ..
+        } => Some(format!(
+            "stack {} has a capacity of {capacity} crates",
+            stack + 1
+        )),
+        CraneError::LiftLimit { limit, .. } => Some(match limit.policy {
+            LiftPolicy::Reject => {
+                "the crane is set to reject lifts over its limit rather than split them".to_string()
+            }
+            LiftPolicy::Split => "the lift couldn't be split: a crate is too heavy on its own, \
+                 or there's no other stack with room to hold the crates in between"
+                .to_string(),
+        }),
+        CraneError::EmptyStack { .. } | CraneError::Instruction(_) => None,
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The gutter is wide enough for the line number.
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f, "error: {}", self.message)?;
+        writeln!(
+            f,
+            "{gutter}--> {}:{}:{}",
+            self.file_name,
+            self.line,
+            self.span.start + 1
+        )?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line, self.source_line)?;
+        // Empty spans (like a missing token at the end of a line) still get one caret.
+        let carets = "^".repeat(self.span.len().max(1));
+        writeln!(f, "{gutter} | {}{carets}", " ".repeat(self.span.start))?;
+        if let Some(hint) = &self.hint {
+            writeln!(f, "{gutter} = hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{ParseMode, PuzzleInput};
+
+    fn diagnostics(source: &str) -> Vec<String> {
+        PuzzleInput::<char>::parse_with(source, ParseMode::Strict)
+            .unwrap_err()
+            .iter()
+            .map(|error| Diagnostic::from_parse_error(error, "input.txt", source).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_stack_diagnostic() {
+        let source = "1 A\n2 B\n\nmove 1 from 1 to 2\nmove 1 from 2 to 10\n";
+        let expected = "error: instruction uses a stack that doesn't exist\n \
+                         --> input.txt:5:18\n  \
+                          |\n\
+                        5 | move 1 from 2 to 10\n  \
+                          |                  ^^\n  \
+                          = hint: stack 10 does not exist; configuration defines 2 stacks\n";
+        assert_eq!(vec![expected], diagnostics(source));
+    }
+
+    #[test]
+    fn test_missing_token_diagnostic() {
+        let source = "1 A\n\nmove 1 from 1";
+        let rendered = &diagnostics(source)[0];
+        assert!(rendered.starts_with("error: expected to\n --> input.txt:3:14\n"));
+        assert!(rendered.contains("3 | move 1 from 1\n  |              ^\n"));
+    }
+
+    #[test]
+    fn test_every_error_gets_a_diagnostic() {
+        let source = "1 A\n\nmove 1 from 1 to 1\nhop\nmove x from 1 to 1\n";
+        let rendered = diagnostics(source);
+        assert_eq!(2, rendered.len());
+        assert!(rendered[0].contains("input.txt:4:1"));
+        assert!(rendered[1].contains("input.txt:5:6"));
+        // Integer parse errors already say what's wrong, so they don't get a hint.
+        assert!(!rendered[1].contains("hint"));
+    }
+
+    #[test]
+    fn test_crane_error_diagnostic() {
+        let source = "1 A B\n2 C\n\nmove 1 from 1 to 2\nmove 3 from 1 to 2\n";
+        let input: PuzzleInput = source.parse().unwrap();
+        let error = input.run(&crate::CrateMover9000).unwrap_err();
+        let rendered = Diagnostic::from_crane_error(&error, "input.txt", source)
+            .unwrap()
+            .to_string();
+        let expected = "error: tried to move 3 crates from stack 1, which only has 1\n \
+                         --> input.txt:5:1\n  \
+                          |\n\
+                        5 | move 3 from 1 to 2\n  \
+                          | ^^^^^^^^^^^^^^^^^^\n  \
+                          = hint: stack 1 has 1 crates at this point, but 3 were requested\n";
+        assert_eq!(expected, rendered);
+
+        let unlocated = CraneError::EmptyStack { stack: 0 };
+        assert_eq!(
+            None,
+            Diagnostic::from_crane_error(&unlocated, "input.txt", source)
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Errors that can occur while parsing the puzzle input or running the crane.
+
+use std::{
+    fmt::{self, Display},
+    io,
+    num::ParseIntError,
+    ops::Range,
+};
+
+use crate::{crane::CraneInstruction, lift::LiftLimit, model::Stacks};
+
+/// An error in the puzzle input, along with where in the input it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line the error is on, starting at 1.
+    pub line: usize,
+    /// The columns of that line the error covers, counted in characters from 0.
+    pub span: Range<usize>,
+    /// The offending text (the characters in `span`).
+    pub text: String,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    // Add different variants as you discover different kinds of parsing errors.
+    /// A stack line with nothing on it, not even a stack number.
+    MissingStackId,
+    /// The stack number isn't a (non-negative) integer.
+    StackIdParseError(ParseIntError),
+    /// Stack numbers start at 1, so 0 is never valid.
+    ZeroStackId,
+    /// Stack numbers start at 1, so negative numbers are never valid.
+    NegativeStackId,
+    /// The same stack was defined twice.
+    DuplicateStackId { first_line: usize },
+    /// The stack number is larger than the largest supported number of stacks.
+    StackIdOutOfRange { max: usize },
+    /// An instruction uses a stack that the stack configuration doesn't define.
+    UnknownStack { num_stacks: usize },
+    /// A crate label that isn't a valid `Label`, or a malformed `[X]` in a
+    /// drawing. `expected` describes what a label should look like.
+    IllegalCrateLabel { expected: &'static str },
+    /// A `capacity` line that isn't `capacity N` or `capacity N for S`.
+    InvalidCapacity,
+    /// The capacity of the same stack, or of all stacks, was set twice.
+    DuplicateCapacity { first_line: usize },
+    /// A stack starts with more crates than the capacity set on this line.
+    OverCapacity { stack: usize, height: usize },
+    /// A crate in a drawing that isn't above any of the stack numbers in the footer.
+    MisalignedCrate,
+    /// An instruction token that isn't what the `move N from A to B` grammar expects.
+    UnexpectedToken { expected: &'static str },
+    /// An instruction that ends before the grammar is complete.
+    MissingToken { expected: &'static str },
+    /// The number of crates to move isn't a (non-negative) integer.
+    CrateCountParseError(ParseIntError),
+    /// A line that doesn't contain exactly three numbers, in lenient mode.
+    InvalidInstruction,
+    /// There's no blank line separating the stacks from the instructions.
+    MissingBlankLine,
+}
+
+impl ParseError {
+    #[must_use]
+    pub fn new(kind: ParseErrorKind, line: usize, span: Range<usize>, text: &str) -> Self {
+        Self {
+            line,
+            span,
+            text: text.to_string(),
+            kind,
+        }
+    }
+
+    /// Move this error to a different line, for when the text that was
+    /// parsed is itself one line of a larger input.
+    #[must_use]
+    pub const fn on_line(mut self, line: usize) -> Self {
+        self.line = line;
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line,
+            self.span.start + 1,
+            self.kind
+        )?;
+        if !self.text.is_empty() {
+            write!(f, " (found `{}`)", self.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ParseErrorKind::StackIdParseError(error)
+            | ParseErrorKind::CrateCountParseError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStackId => write!(f, "missing stack number"),
+            Self::StackIdParseError(error) => write!(f, "invalid stack number ({error})"),
+            Self::ZeroStackId | Self::NegativeStackId => write!(f, "stack numbers start at 1"),
+            Self::DuplicateStackId { first_line } => {
+                write!(f, "stack was already defined on line {first_line}")
+            }
+            Self::StackIdOutOfRange { max } => {
+                write!(f, "stack number is larger than the maximum of {max}")
+            }
+            Self::UnknownStack { .. } => write!(f, "instruction uses a stack that doesn't exist"),
+            Self::IllegalCrateLabel { expected } => write!(f, "crate labels must be {expected}"),
+            Self::InvalidCapacity => write!(f, "invalid capacity"),
+            Self::DuplicateCapacity { first_line } => {
+                write!(f, "capacity was already set on line {first_line}")
+            }
+            Self::OverCapacity { stack, height } => write!(
+                f,
+                "stack {} starts with {height} crates, more than its capacity",
+                stack + 1
+            ),
+            Self::MisalignedCrate => write!(f, "crate is not above any stack number"),
+            Self::UnexpectedToken { expected } | Self::MissingToken { expected } => {
+                write!(f, "expected {expected}")
+            }
+            Self::CrateCountParseError(error) => write!(f, "invalid number of crates ({error})"),
+            Self::InvalidInstruction => {
+                write!(f, "expected an instruction like `move 1 from 2 to 3`")
+            }
+            Self::MissingBlankLine => write!(
+                f,
+                "expected a blank line between the stacks and the instructions"
+            ),
+        }
+    }
+}
+
+/// An error from running the crane. Errors from applying a list of
+/// instructions are wrapped in `CraneError::Instruction`, which says
+/// which instruction failed and what the stacks looked like beforehand.
+///
+/// Stack numbers in these errors are zero-based indices into `Stacks`.
+/// `L` is the type of crate label, as in `Stacks<L>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CraneError<L = char> {
+    // Add different variants as you discover different kinds of errors
+    // that can occur when applying a crane instruction.
+    /// An instruction uses a stack that doesn't exist.
+    InvalidStack { stack: usize, num_stacks: usize },
+    /// Tried to take the top of an empty stack.
+    EmptyStack { stack: usize },
+    /// Tried to move more crates than the stack holds.
+    InvalidMove {
+        from_stack: usize,
+        requested: usize,
+        available: usize,
+    },
+    /// An instruction would put more crates on a stack than its capacity,
+    /// by `excess` crates.
+    Overflow {
+        stack: usize,
+        capacity: usize,
+        excess: usize,
+    },
+    /// An instruction is more than the crane can lift at once, and
+    /// couldn't be split into smaller lifts.
+    LiftLimit {
+        crates: usize,
+        /// The total weight of the crates, if the limit includes a weight.
+        weight: u64,
+        limit: LiftLimit,
+    },
+    /// One of the errors above, from one of a list of instructions.
+    Instruction(Box<InstructionFailure<L>>),
+}
+
+/// Where in a list of instructions things went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionFailure<L = char> {
+    /// The position of the failed instruction in the list, starting at 0.
+    pub index: usize,
+    /// The line of the input the instruction came from, if we know it.
+    pub line: Option<usize>,
+    pub instruction: CraneInstruction,
+    /// The stacks immediately before the failed instruction.
+    pub before: Stacks<L>,
+    /// What went wrong.
+    pub cause: CraneError<L>,
+}
+
+impl<L> CraneError<L> {
+    /// The underlying error, without any instruction context.
+    #[must_use]
+    pub fn cause(&self) -> &Self {
+        match self {
+            Self::Instruction(failure) => failure.cause.cause(),
+            _ => self,
+        }
+    }
+
+    /// Record the input line of the failed instruction, if this came from one.
+    #[must_use]
+    pub fn on_line(mut self, line: usize) -> Self {
+        if let Self::Instruction(failure) = &mut self {
+            failure.line = Some(line);
+        }
+        self
+    }
+}
+
+impl<L> Display for CraneError<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidStack { stack, num_stacks } => write!(
+                f,
+                "stack {} does not exist; there are {num_stacks} stacks",
+                stack + 1
+            ),
+            Self::EmptyStack { stack } => {
+                write!(
+                    f,
+                    "tried to take the top of stack {}, which is empty",
+                    stack + 1
+                )
+            }
+            Self::InvalidMove {
+                from_stack,
+                requested,
+                available,
+            } => write!(
+                f,
+                "tried to move {requested} crates from stack {}, which only has {available}",
+                from_stack + 1
+            ),
+            Self::Overflow {
+                stack,
+                capacity,
+                excess,
+            } => write!(
+                f,
+                "stack {} can only hold {capacity} crates, and this puts {excess} too many on it",
+                stack + 1
+            ),
+            Self::LiftLimit {
+                crates,
+                weight,
+                limit,
+            } => {
+                write!(f, "the crane can't lift {crates} crates")?;
+                if limit.max_weight.is_some() {
+                    write!(f, " weighing {weight}kg")?;
+                }
+                write!(f, " at once; it can lift at most ")?;
+                match (limit.max_crates, limit.max_weight) {
+                    (Some(crates), Some(weight)) => write!(f, "{crates} crates and {weight}kg"),
+                    (Some(crates), None) => write!(f, "{crates} crates"),
+                    (None, Some(weight)) => write!(f, "{weight}kg"),
+                    (None, None) => write!(f, "anything"),
+                }
+            }
+            Self::Instruction(failure) => {
+                write!(f, "instruction {} ", failure.index + 1)?;
+                if let Some(line) = failure.line {
+                    write!(f, "on line {line} ")?;
+                }
+                write!(f, "(`{}`) failed: {}", failure.instruction, failure.cause)
+            }
+        }
+    }
+}
+
+impl<L: fmt::Debug + 'static> std::error::Error for CraneError<L> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Instruction(failure) => Some(&failure.cause),
+            _ => None,
+        }
+    }
+}
+
+/// Why `Planner::plan` couldn't come up with a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// The goal doesn't have the same number of stacks as the start.
+    WrongStackCount { expected: usize, found: usize },
+    /// The goal needs different crates than the start has.
+    DifferentCrates,
+    /// Every configuration the crane can reach was tried, and none of
+    /// them meet the goal.
+    Unreachable,
+    /// The search looked at this many configurations without meeting the
+    /// goal, and gave up.
+    LimitReached { explored: usize },
+    /// Replaying the plan from the start failed.
+    Replay(CraneError),
+    /// Replaying the plan from the start didn't meet the goal.
+    GoalMissed,
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongStackCount { expected, found } => write!(
+                f,
+                "the goal has {found} stacks, but the starting configuration has {expected}"
+            ),
+            Self::DifferentCrates => write!(
+                f,
+                "the goal needs different crates than the starting configuration has"
+            ),
+            Self::Unreachable => write!(f, "the goal can't be reached with this crane"),
+            Self::LimitReached { explored } => write!(
+                f,
+                "gave up after exploring {explored} configurations without reaching the goal"
+            ),
+            Self::Replay(error) => write!(f, "the plan failed when replayed: {error}"),
+            Self::GoalMissed => write!(f, "the plan doesn't reach the goal when replayed"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Replay(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Why reading and applying an input as a stream failed.
+#[derive(Debug)]
+pub enum StreamError {
+    /// Reading the input failed.
+    Io(io::Error),
+    /// A line of the input isn't valid. The line number is a line of the
+    /// whole input.
+    Parse(ParseError),
+    /// An instruction couldn't be applied.
+    Crane(CraneError),
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ParseError> for StreamError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<CraneError> for StreamError {
+    fn from(error: CraneError) -> Self {
+        Self::Crane(error)
+    }
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read the input: {error}"),
+            Self::Parse(error) => write!(f, "{error}"),
+            Self::Crane(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse(error) => Some(error),
+            Self::Crane(error) => Some(error),
+        }
+    }
+}
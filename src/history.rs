@@ -0,0 +1,271 @@
+//! Stepping backwards and forwards through a list of instructions.
+//!
+//! Applying an instruction drains crates off one stack and onto another,
+//! so the stacks alone don't say how to get back to where they were. A
+//! [`History`] remembers how tall the two stacks of each move were and
+//! the crates the move could take off its source stack, which is enough
+//! to undo it exactly, however many of them the crane really moved. That makes it cheap to
+//! look at the stacks after any step, e.g. "what if we stop after step
+//! 312", without re-running everything from the start.
+
+use crate::{
+    crane::{Crane, CraneInstruction, CraneInstructions},
+    error::{CraneError, InstructionFailure},
+    model::Stacks,
+};
+
+/// A set of stacks part way through a list of instructions, that can be
+/// moved to any point in the list. Created by [`Stacks::history`].
+#[derive(Debug)]
+pub struct History<'a, C: ?Sized> {
+    stacks: Stacks,
+    crane: &'a C,
+    instructions: &'a [CraneInstruction],
+    /// What each applied instruction needs to undo it. There's one entry
+    /// per applied instruction, so its length is the current position.
+    applied: Vec<Applied>,
+}
+
+/// The stacks of one move as they were before it.
+#[derive(Debug)]
+struct Applied {
+    from_height: usize,
+    to_height: usize,
+    /// The crates the move was allowed to lift off the source stack,
+    /// bottom to top.
+    lifted: Vec<char>,
+}
+
+impl Stacks {
+    /// Start a history of applying `instructions` to these stacks. No
+    /// instructions are applied yet.
+    #[must_use]
+    pub fn history<'a, C: Crane + ?Sized>(
+        self,
+        crane: &'a C,
+        instructions: &'a CraneInstructions,
+    ) -> History<'a, C> {
+        History {
+            stacks: self,
+            crane,
+            instructions: &instructions.instructions,
+            applied: Vec::new(),
+        }
+    }
+}
+
+impl<C: Crane + ?Sized> History<'_, C> {
+    /// The stacks at the current position.
+    #[must_use]
+    pub const fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+
+    /// How many instructions have been applied.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.applied.len()
+    }
+
+    /// The total number of instructions.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Apply the next instruction. Returns `false` if they've all been
+    /// applied already.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CraneError::Instruction` if the next instruction can't
+    /// be applied, in which case the position doesn't change.
+    pub fn redo(&mut self) -> Result<bool, CraneError> {
+        let index = self.position();
+        let Some(instruction) = self.instructions.get(index) else {
+            return Ok(false);
+        };
+        // A stack capacity might mean fewer crates are moved, and a lift
+        // limit might mean they're moved in several lifts.
+        let applied = match self.stacks.plan_lifts(self.crane, instruction) {
+            Ok(lifts) => {
+                let from = &self.stacks.stacks[instruction.from_stack].stack;
+                let applied = Applied {
+                    from_height: from.len(),
+                    to_height: self.stacks.stacks[instruction.to_stack].len(),
+                    lifted: from[from.len() - lifts.admitted.num_to_move..].to_vec(),
+                };
+                for lift in lifts.as_slice() {
+                    self.stacks.lift(self.crane, lift);
+                }
+                applied
+            }
+            Err(cause) => {
+                return Err(CraneError::Instruction(Box::new(InstructionFailure {
+                    index,
+                    line: None,
+                    instruction: *instruction,
+                    before: self.stacks.clone(),
+                    cause,
+                })));
+            }
+        };
+        self.applied.push(applied);
+        Ok(true)
+    }
+
+    /// Undo the last instruction applied. Returns `false` if there's
+    /// nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(applied) = self.applied.pop() else {
+            return false;
+        };
+        let instruction = self.instructions[self.applied.len()];
+        // Whatever the crane did with them, the crates it moved are above
+        // the old height of the destination stack, and any it didn't move
+        // are still in the lifted part of the source stack. So cut both
+        // stacks back and put the lifted crates back on. This also works
+        // when both stacks are the same.
+        self.stacks.stacks[instruction.to_stack]
+            .stack
+            .truncate(applied.to_height);
+        let from = &mut self.stacks.stacks[instruction.from_stack].stack;
+        from.truncate(applied.from_height - applied.lifted.len());
+        from.extend(applied.lifted);
+        true
+    }
+
+    /// Undo or redo instructions until exactly `position` of them have
+    /// been applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `redo` if an instruction on the way can't
+    /// be applied, leaving the history just before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` is more than the number of instructions.
+    pub fn jump_to(&mut self, position: usize) -> Result<(), CraneError> {
+        assert!(
+            position <= self.len(),
+            "can't jump to step {position} of {}",
+            self.len()
+        );
+        while self.position() > position {
+            self.undo();
+        }
+        while self.position() < position {
+            self.redo()?;
+        }
+        Ok(())
+    }
+
+    /// Stop and take the stacks at the current position.
+    #[must_use]
+    pub fn into_stacks(self) -> Stacks {
+        self.stacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crane::{CraneModel, CrateMover9000},
+        parse::PuzzleInput,
+        test_util::TopOnly,
+    };
+
+    const EXAMPLE: &str = "1 Z N\n2 M C D\n3 P\n\n\
+                           move 1 from 2 to 1\n\
+                           move 3 from 1 to 3\n\
+                           move 2 from 2 to 2\n\
+                           move 1 from 3 to 1\n";
+
+    #[test]
+    fn test_undo_restores_every_step() {
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let input: PuzzleInput = EXAMPLE.parse().unwrap();
+            let mut history = input.stacks.clone().history(&crane, &input.instructions);
+            let mut states = vec![history.stacks().clone()];
+            while history.redo().unwrap() {
+                states.push(history.stacks().clone());
+            }
+            assert_eq!(&input.run(&crane).unwrap(), history.stacks());
+
+            while let Some(expected) = states.pop() {
+                assert_eq!(&expected, history.stacks());
+                history.undo();
+            }
+            assert!(!history.undo());
+        }
+    }
+
+    #[test]
+    fn test_undo_clamped_moves() {
+        let mut stacks: Stacks = "capacity 3\n1 A B C\n2 D E".parse().unwrap();
+        stacks.set_overflow_policy(crate::capacity::OverflowPolicy::Clamp);
+        let instructions: CraneInstructions = "move 3 from 1 to 2".parse().unwrap();
+        let mut history = stacks.clone().history(&CrateMover9000, &instructions);
+        assert!(history.redo().unwrap());
+        assert_eq!(vec!['A', 'B'], history.stacks().stacks[0].stack);
+        assert!(history.undo());
+        assert_eq!(&stacks, history.stacks());
+    }
+
+    #[test]
+    fn test_undo_crane_without_landing() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let mut history = input.stacks.clone().history(&TopOnly, &input.instructions);
+        let mut states = vec![history.stacks().clone()];
+        while history.redo().unwrap() {
+            states.push(history.stacks().clone());
+        }
+        assert_eq!(&input.run(&TopOnly).unwrap(), history.stacks());
+
+        while let Some(expected) = states.pop() {
+            assert_eq!(&expected, history.stacks());
+            history.undo();
+        }
+        history.jump_to(4).unwrap();
+        assert_eq!(&input.run(&TopOnly).unwrap(), history.stacks());
+    }
+
+    #[test]
+    fn test_jump_to() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let mut history = input
+            .stacks
+            .clone()
+            .history(&CrateMover9000, &input.instructions);
+        history.jump_to(4).unwrap();
+        history.jump_to(1).unwrap();
+        assert_eq!(1, history.position());
+        let after_one = input
+            .stacks
+            .clone()
+            .apply_instruction(&CrateMover9000, input.instructions.iter().next().unwrap())
+            .unwrap();
+        assert_eq!(after_one, history.into_stacks());
+    }
+
+    #[test]
+    fn test_redo_failure_keeps_position() {
+        let input: PuzzleInput = "1 A\n2 B\n\nmove 1 from 1 to 2\nmove 1 from 1 to 2\n"
+            .parse()
+            .unwrap();
+        let mut history = input
+            .stacks
+            .clone()
+            .history(&CrateMover9000, &input.instructions);
+        let error = history.jump_to(2).unwrap_err();
+        assert!(matches!(error.cause(), CraneError::InvalidMove { .. }));
+        assert_eq!(1, history.position());
+    }
+}
@@ -0,0 +1,69 @@
+//! A simulation of the supply stacks and cargo crane from
+//! [Advent of Code 2022, Day 5](https://adventofcode.com/2022/day/5).
+//!
+//! The library is split into a few small modules:
+//!
+//! - [`model`] holds the stacks of crates themselves (`Stacks` and `Stack`),
+//!   which can be labelled with characters, words, or numbers.
+//! - [`record`] adds weight, fragility, and destination tags to crate labels.
+//! - [`parse`] turns the puzzle input into those types via `FromStr`.
+//! - [`capacity`] limits how many crates each stack can hold.
+//! - [`crane`] describes crane instructions and applies them to the stacks.
+//! - [`lift`] limits how much a crane can lift at once, splitting moves if needed.
+//! - [`backward`] works out the top crates by walking the instructions backwards.
+//! - [`rope`] stores stacks as trees, so huge moves take the same time as small ones.
+//! - [`stream`] applies instructions as they're read, for very large inputs.
+//! - [`track`] gives crates identities and follows where each one goes.
+//! - [`trace`] applies instructions one step at a time, for debugging.
+//! - [`history`] steps backwards and forwards through instructions.
+//! - [`optimize`] shortens lists of instructions without changing what they do.
+//! - [`plan`] searches for instructions that reach a goal configuration.
+//! - [`cost`] estimates how long instructions take on the real crane.
+//! - [`render`] draws the stacks as the column picture from the puzzle.
+//! - [`error`] holds the errors that parsing and applying instructions can produce.
+//! - [`diagnostic`] renders those errors like a compiler, pointing at the input.
+//!
+//! The most commonly used types are re-exported from the crate root, so
+//! `use supply_stacks::{Stacks, CraneInstructions};` is usually all you need.
+
+pub mod backward;
+pub mod capacity;
+pub mod cost;
+pub mod crane;
+pub mod diagnostic;
+pub mod error;
+pub mod history;
+pub mod lift;
+pub mod model;
+pub mod optimize;
+pub mod parse;
+pub mod plan;
+pub mod record;
+pub mod render;
+pub mod rope;
+pub mod stream;
+pub mod trace;
+pub mod track;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+pub use capacity::{Capacity, OverflowPolicy};
+pub use cost::{CostModel, CostReport, InstructionCost, LinearCost, StackTraffic};
+pub use crane::{
+    Crane, CraneInstruction, CraneInstructions, CraneModel, CrateMover9000, CrateMover9001, Landing,
+};
+pub use diagnostic::Diagnostic;
+pub use error::{
+    CraneError, InstructionFailure, ParseError, ParseErrorKind, PlanError, StreamError,
+};
+pub use history::History;
+pub use lift::{LiftLimit, LiftPolicy, Limited};
+pub use model::{Label, Stack, Stacks, TopsReport};
+pub use parse::{InstructionReport, ParseMode, PuzzleInput, StackFormat};
+pub use plan::{Goal, Objective, Planner};
+pub use record::CrateRecord;
+pub use rope::{RopeStacks, StackBackend};
+pub use stream::InputStream;
+pub use trace::{Step, Trace};
+pub use track::{CrateId, Location, Movement, TrackedStacks};
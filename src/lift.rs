@@ -0,0 +1,500 @@
+//! Cranes that can only lift so much at once.
+//!
+//! `CrateMover9001` lifts any number of crates in one go. Wrapping a
+//! crane in [`Limited`] gives it a [`LiftLimit`]: a maximum number of
+//! crates per lift, a maximum weight per lift (using the weights of
+//! `CrateRecord` labels), or both. An instruction over the limit either
+//! fails with `CraneError::LiftLimit`, or is split into smaller lifts that
+//! leave the stacks exactly as the one big lift would have.
+//!
+//! A crane that moves crates one at a time can simply make the smaller
+//! lifts one after another. A crane that keeps the group in order has to
+//! put the smaller lifts down on a third, buffer stack first, and then
+//! move them from there in the opposite order, so the group ends up the
+//! right way round.
+
+use std::slice;
+
+use crate::{
+    capacity::Capacity,
+    crane::{Crane, CraneInstruction, CraneInstructions, Landing},
+    error::{CraneError, InstructionFailure},
+    model::{Label, Stack, Stacks},
+};
+
+/// What to do with an instruction that's over the crane's limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LiftPolicy {
+    /// The instruction fails with `CraneError::LiftLimit`.
+    #[default]
+    Reject,
+    /// The instruction is carried out as several smaller lifts. If that
+    /// isn't possible it fails as with `Reject`.
+    Split,
+}
+
+/// How much a crane can lift at once. By default there's no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LiftLimit {
+    pub max_crates: Option<usize>,
+    /// In kilograms. Only `CrateRecord` labels have a weight.
+    pub max_weight: Option<u64>,
+    pub policy: LiftPolicy,
+}
+
+impl LiftLimit {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_crates: None,
+            max_weight: None,
+            policy: LiftPolicy::Reject,
+        }
+    }
+
+    #[must_use]
+    pub const fn max_crates(mut self, max_crates: usize) -> Self {
+        self.max_crates = Some(max_crates);
+        self
+    }
+
+    #[must_use]
+    pub const fn max_weight(mut self, max_weight: u64) -> Self {
+        self.max_weight = Some(max_weight);
+        self
+    }
+
+    #[must_use]
+    pub const fn policy(mut self, policy: LiftPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Whether this doesn't limit anything.
+    #[must_use]
+    pub const fn is_unlimited(&self) -> bool {
+        self.max_crates.is_none() && self.max_weight.is_none()
+    }
+
+    /// Whether a lift of `crates` crates weighing `weight` is allowed.
+    #[must_use]
+    pub fn allows(&self, crates: usize, weight: u64) -> bool {
+        self.max_crates.is_none_or(|max| crates <= max)
+            && self.max_weight.is_none_or(|max| weight <= max)
+    }
+
+    /// The lifts that carry out `instruction`, which has already been
+    /// checked against the stacks. `weight(i)` is the weight of the `i`th
+    /// moved crate counting down from the top, and `room(stack)` is how
+    /// many more crates a stack can take.
+    pub(crate) fn lifts<L>(
+        &self,
+        landing: Option<Landing>,
+        instruction: &CraneInstruction,
+        num_stacks: usize,
+        weight: impl Fn(usize) -> u64,
+        room: impl Fn(usize) -> usize,
+    ) -> Result<Vec<CraneInstruction>, CraneError<L>> {
+        let CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        } = *instruction;
+        // Crates only have weights that matter if there's a weight limit.
+        let total = if self.max_weight.is_some() {
+            (0..num_to_move).map(&weight).sum()
+        } else {
+            0
+        };
+        if self.allows(num_to_move, total) {
+            return Ok(vec![*instruction]);
+        }
+        let too_much = || CraneError::LiftLimit {
+            crates: num_to_move,
+            weight: total,
+            limit: *self,
+        };
+        if self.policy == LiftPolicy::Reject {
+            return Err(too_much());
+        }
+
+        let sizes = self.split(num_to_move, &weight).ok_or_else(too_much)?;
+        let lift = |num_to_move, from_stack, to_stack| CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        };
+        match landing {
+            Some(Landing::Reversed) if from_stack != to_stack => Ok(sizes
+                .iter()
+                .map(|&size| lift(size, from_stack, to_stack))
+                .collect()),
+            // Putting a group back where it was in order changes nothing.
+            Some(Landing::InOrder) if from_stack == to_stack => Ok(Vec::new()),
+            Some(Landing::InOrder) => {
+                let buffer = (0..num_stacks)
+                    .find(|&stack| {
+                        stack != from_stack && stack != to_stack && room(stack) >= num_to_move
+                    })
+                    .ok_or_else(too_much)?;
+                Ok(sizes
+                    .iter()
+                    .map(|&size| lift(size, from_stack, buffer))
+                    .chain(sizes.iter().rev().map(|&size| lift(size, buffer, to_stack)))
+                    .collect())
+            }
+            // Otherwise we don't know a way of splitting the lift that
+            // leaves the crates in the same order.
+            _ => Err(too_much()),
+        }
+    }
+
+    /// Split `num_to_move` crates into lifts within the limit, taking as
+    /// many as possible each time, top first. Returns `None` if a single
+    /// crate is too heavy, or the limit is no crates at all.
+    fn split(&self, num_to_move: usize, weight: impl Fn(usize) -> u64) -> Option<Vec<usize>> {
+        let max_crates = self.max_crates.unwrap_or(usize::MAX);
+        let mut sizes = Vec::new();
+        let mut start = 0;
+        while start < num_to_move {
+            let (mut size, mut load) = (0, 0);
+            while start + size < num_to_move && size < max_crates {
+                let next = load + weight(start + size);
+                if self.max_weight.is_some_and(|max| next > max) {
+                    break;
+                }
+                (size, load) = (size + 1, next);
+            }
+            if size == 0 {
+                return None;
+            }
+            sizes.push(size);
+            start += size;
+        }
+        Some(sizes)
+    }
+}
+
+/// The lifts a crane makes to carry out one instruction, worked out by
+/// [`Lifts::plan`]. Every way of applying instructions goes through this,
+/// so they all check, clamp, and split instructions the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lifts {
+    /// The instruction after any stack capacity clamped it.
+    pub(crate) admitted: CraneInstruction,
+    /// The smaller lifts it was split into, or `None` if it's one lift.
+    split: Option<Vec<CraneInstruction>>,
+}
+
+impl Lifts {
+    /// Check `instruction` against `num_stacks` stacks, where `height`
+    /// gives the number of crates on each, then apply `capacity` and split
+    /// the result if it's over `limit`. `weight(i)` is the weight of the
+    /// `i`th crate counting down from the top of the source stack.
+    pub(crate) fn plan<L>(
+        instruction: &CraneInstruction,
+        num_stacks: usize,
+        capacity: &Capacity,
+        height: impl Fn(usize) -> usize,
+        landing: Option<Landing>,
+        limit: Option<&LiftLimit>,
+        weight: impl Fn(usize) -> u64,
+    ) -> Result<Self, CraneError<L>> {
+        instruction.check(num_stacks, &height)?;
+        let admitted = capacity.admit(instruction, &height)?;
+        let split = limit
+            .map(|limit| {
+                limit.lifts(landing, &admitted, num_stacks, weight, |stack| {
+                    capacity.room(stack, height(stack))
+                })
+            })
+            .transpose()?;
+        Ok(Self { admitted, split })
+    }
+
+    pub(crate) fn as_slice(&self) -> &[CraneInstruction] {
+        self.split
+            .as_deref()
+            .unwrap_or_else(|| slice::from_ref(&self.admitted))
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<CraneInstruction> {
+        self.split.unwrap_or_else(|| vec![self.admitted])
+    }
+}
+
+/// A crane with a `LiftLimit`. Apart from the limit it behaves exactly
+/// like the crane it wraps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limited<C> {
+    pub crane: C,
+    pub limit: LiftLimit,
+}
+
+impl<C> Limited<C> {
+    #[must_use]
+    pub const fn new(crane: C, limit: LiftLimit) -> Self {
+        Self { crane, limit }
+    }
+}
+
+impl<C: Crane> Crane for Limited<C> {
+    fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize) {
+        self.crane.transfer(from, to, n);
+    }
+
+    fn inverse(&self, instruction: &CraneInstruction) -> Option<CraneInstruction> {
+        self.crane.inverse(instruction)
+    }
+
+    fn landing(&self) -> Option<Landing> {
+        self.crane.landing()
+    }
+
+    fn is_no_op(&self, instruction: &CraneInstruction) -> bool {
+        self.crane.is_no_op(instruction)
+    }
+
+    // Merging lifts could go over the limit, which is only safe if big
+    // lifts get split again. We don't know the crates' weights here.
+    fn merge(
+        &self,
+        first: &CraneInstruction,
+        second: &CraneInstruction,
+    ) -> Option<CraneInstruction> {
+        self.crane.merge(first, second).filter(|merged| {
+            self.limit.policy == LiftPolicy::Split
+                || (self.limit.max_weight.is_none() && self.limit.allows(merged.num_to_move, 0))
+        })
+    }
+
+    fn lift_limit(&self) -> Option<&LiftLimit> {
+        Some(&self.limit).filter(|limit| !limit.is_unlimited())
+    }
+}
+
+impl<L: Label> Stacks<L> {
+    /// The lifts `crane` makes to carry out `instruction` on these stacks.
+    /// That's just the instruction unless it's over the crane's limit and
+    /// has to be split, or a full stack means fewer crates are moved.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `apply_instruction` would.
+    pub fn lifts<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        instruction: &CraneInstruction,
+    ) -> Result<Vec<CraneInstruction>, CraneError<L>> {
+        Ok(self.plan_lifts(crane, instruction)?.into_vec())
+    }
+
+    /// Like `lifts`, without allocating when there's only one lift.
+    pub(crate) fn plan_lifts<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        instruction: &CraneInstruction,
+    ) -> Result<Lifts, CraneError<L>> {
+        Lifts::plan(
+            instruction,
+            self.len(),
+            &self.capacity,
+            |stack| self.stacks[stack].len(),
+            crane.landing(),
+            crane.lift_limit(),
+            // Only called once the instruction has been checked.
+            |i| {
+                let from = &self.stacks[instruction.from_stack].stack;
+                from[from.len() - 1 - i].weight()
+            },
+        )
+    }
+}
+
+impl<L: Label + Clone> Stacks<L> {
+    /// Every lift `crane` makes to carry out `instructions`, as a list of
+    /// instructions that are all within its limit and do the same thing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `apply_instructions` would.
+    pub fn lift_plan<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        instructions: &CraneInstructions,
+    ) -> Result<CraneInstructions, CraneError<L>> {
+        let mut plan = Vec::new();
+        self.for_each_lifts(crane, instructions, |_, _, lifts| {
+            plan.extend_from_slice(lifts);
+        })?;
+        Ok(plan.into_iter().collect())
+    }
+
+    /// Carry out `instructions` on a copy of these stacks, calling `f`
+    /// with the index of each instruction, the instruction, and the lifts
+    /// made for it.
+    pub(crate) fn for_each_lifts<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        instructions: &CraneInstructions,
+        mut f: impl FnMut(usize, &CraneInstruction, &[CraneInstruction]),
+    ) -> Result<(), CraneError<L>> {
+        let mut stacks = self.clone();
+        for (index, instruction) in instructions.iter().enumerate() {
+            let lifts = match stacks.plan_lifts(crane, instruction) {
+                Ok(lifts) => lifts,
+                Err(cause) => {
+                    return Err(CraneError::Instruction(Box::new(InstructionFailure {
+                        index,
+                        line: None,
+                        instruction: *instruction,
+                        before: stacks,
+                        cause,
+                    })));
+                }
+            };
+            for lift in lifts.as_slice() {
+                stacks.lift(crane, lift);
+            }
+            f(index, instruction, lifts.as_slice());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crane::{CraneModel, CrateMover9000, CrateMover9001},
+        parse::PuzzleInput,
+        record::CrateRecord,
+        rope::StackBackend,
+    };
+
+    const EXAMPLE: &str = "1 A B C D E\n2 F\n3\n\nmove 5 from 1 to 2\nmove 2 from 2 to 3\n";
+
+    #[test]
+    fn test_reject() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let crane = Limited::new(CrateMover9001, LiftLimit::new().max_crates(3));
+        let error = input.run(&crane).unwrap_err();
+        assert_eq!(
+            &CraneError::LiftLimit {
+                crates: 5,
+                weight: 0,
+                limit: crane.limit,
+            },
+            error.cause()
+        );
+        assert_eq!(
+            "instruction 1 on line 5 (`move 5 from 1 to 2`) failed: \
+             the crane can't lift 5 crates at once; it can lift at most 3 crates",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_split_matches_unlimited_crane() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        for model in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let limit = LiftLimit::new().max_crates(2).policy(LiftPolicy::Split);
+            let crane = Limited::new(model, limit);
+            let expected = input.run(&model).unwrap();
+            assert_eq!(expected, input.run(&crane).unwrap());
+            assert_eq!(
+                expected,
+                input.run_with(&crane, StackBackend::Rope).unwrap()
+            );
+            assert_eq!(expected.tops_string(), input.tops(&crane));
+
+            let mut tracked = input.stacks.tracked(&crane).unwrap();
+            tracked.apply_instructions(&input.instructions).unwrap();
+            assert_eq!(expected, tracked.to_stacks());
+
+            // The plan only has lifts the crane can make without splitting.
+            let plan = input.stacks.lift_plan(&crane, &input.instructions).unwrap();
+            assert!(plan.iter().all(|lift| lift.num_to_move <= 2));
+            let strict = Limited::new(model, LiftLimit::new().max_crates(2));
+            assert_eq!(
+                expected,
+                input
+                    .stacks
+                    .clone()
+                    .apply_instructions(&strict, &plan)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_order_lifts_use_a_buffer_stack() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let limit = LiftLimit::new().max_crates(2).policy(LiftPolicy::Split);
+        let crane = Limited::new(CrateMover9001, limit);
+        let plan = input.stacks.lift_plan(&crane, &input.instructions).unwrap();
+        let expected: CraneInstructions = "move 2 from 1 to 3\n\
+                                           move 2 from 1 to 3\n\
+                                           move 1 from 1 to 3\n\
+                                           move 1 from 3 to 2\n\
+                                           move 2 from 3 to 2\n\
+                                           move 2 from 3 to 2\n\
+                                           move 2 from 2 to 3"
+            .parse()
+            .unwrap();
+        assert_eq!(expected, plan);
+
+        // Without a third stack there's nowhere to put the lifts down.
+        let two_stacks: PuzzleInput = "1 A B C\n2\n\nmove 3 from 1 to 2\n".parse().unwrap();
+        assert!(matches!(
+            two_stacks.run(&crane).unwrap_err().cause(),
+            CraneError::LiftLimit { .. }
+        ));
+        let one_at_a_time = Limited::new(CrateMover9000, limit);
+        assert!(two_stacks.run(&one_at_a_time).is_ok());
+    }
+
+    #[test]
+    fn test_weight_limit() {
+        let input: PuzzleInput<CrateRecord> = "1 A:5kg B:6kg C:4kg\n2\n3\n\nmove 3 from 1 to 2\n"
+            .parse()
+            .unwrap();
+        let limit = LiftLimit::new().max_weight(10);
+        let error = input.run(&Limited::new(CrateMover9001, limit)).unwrap_err();
+        assert!(matches!(
+            error.cause(),
+            CraneError::LiftLimit { weight: 15, .. }
+        ));
+
+        let crane = Limited::new(CrateMover9001, limit.policy(LiftPolicy::Split));
+        let plan = input.stacks.lift_plan(&crane, &input.instructions).unwrap();
+        // C and B weigh 10kg together, which leaves A on its own.
+        assert_eq!(
+            vec![2, 1, 1, 2],
+            plan.iter().map(|lift| lift.num_to_move).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            input.run(&CrateMover9001).unwrap(),
+            input.run(&crane).unwrap()
+        );
+
+        let heavy: PuzzleInput<CrateRecord> =
+            "1 A:11kg\n2\n3\n\nmove 1 from 1 to 2\n".parse().unwrap();
+        assert!(heavy.run(&crane).is_err());
+    }
+
+    #[test]
+    fn test_merges_stay_within_the_limit() {
+        let crane = Limited::new(CrateMover9000, LiftLimit::new().max_crates(3));
+        let instruction = |num_to_move| CraneInstruction {
+            num_to_move,
+            from_stack: 0,
+            to_stack: 1,
+        };
+        assert_eq!(
+            Some(instruction(3)),
+            crane.merge(&instruction(1), &instruction(2))
+        );
+        assert_eq!(None, crane.merge(&instruction(2), &instruction(2)));
+    }
+}
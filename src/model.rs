@@ -0,0 +1,368 @@
+//! The stacks of crates that the crane rearranges.
+//!
+//! The puzzle labels crates with single characters, but the stacks can
+//! hold any [`Label`], so crates can also be labelled with words such as
+//! SKU codes, or with numbers. `char` is the default everywhere.
+
+use std::fmt::{self, Display};
+
+use crate::{capacity::Capacity, error::CraneError};
+
+/// Something a crate can be labelled with. In the input each label is a
+/// single whitespace-separated token.
+pub trait Label: Clone + Eq + fmt::Debug + Display {
+    /// What a valid label looks like, for error messages.
+    const EXPECTED: &'static str;
+
+    /// Read a label from one token of the input, or return `None` if the
+    /// token isn't a valid label.
+    fn from_token(token: &str) -> Option<Self>;
+
+    /// The weight of a crate with this label in kilograms, for cranes
+    /// that can only lift so much. Crates weigh nothing unless their
+    /// label says otherwise.
+    fn weight(&self) -> u64 {
+        0
+    }
+}
+
+// Brackets can't be labels since they'd be ambiguous in a drawing.
+impl Label for char {
+    const EXPECTED: &'static str = "a single character other than `[` or `]`";
+
+    fn from_token(token: &str) -> Option<Self> {
+        let mut chars = token.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c != '[' && c != ']' => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl Label for String {
+    const EXPECTED: &'static str = "a word without `[` or `]`";
+
+    fn from_token(token: &str) -> Option<Self> {
+        (!token.contains(['[', ']'])).then(|| token.to_string())
+    }
+}
+
+impl Label for u32 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_token(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+}
+
+impl Label for u64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_token(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+}
+
+/// All the stacks in the supply yard. There can be any number of them;
+/// the parser sizes this from the highest stack number in the input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stacks<L = char> {
+    pub(crate) stacks: Vec<Stack<L>>,
+    pub(crate) capacity: Capacity,
+}
+
+// Deriving `Default` would needlessly require `L: Default`.
+impl<L> Default for Stacks<L> {
+    fn default() -> Self {
+        Self {
+            stacks: Vec::new(),
+            capacity: Capacity::default(),
+        }
+    }
+}
+
+impl<L> Stacks<L> {
+    /// A yard of `num_stacks` empty stacks.
+    #[must_use]
+    pub fn with_len(num_stacks: usize) -> Self {
+        (0..num_stacks).map(|_| Stack::default()).collect()
+    }
+
+    /// The number of stacks (including empty ones).
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.stacks.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    /// Return the stack with the given (zero-based) index, if there is one.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&Stack<L>> {
+        self.stacks.get(index)
+    }
+
+    /// Iterate over the stacks, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Stack<L>> {
+        self.stacks.iter()
+    }
+}
+
+impl<L: Display> Stacks<L> {
+    /// Return a string containing the top crate of each stack in order.
+    /// The stacks should all be non-empty; if any is empty return a `CraneError`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CraneError::EmptyStack` if any of the stacks is empty.
+    pub fn tops_string(&self) -> Result<String, CraneError<L>> {
+        self.tops_report(&TopsReport::new())
+    }
+
+    /// Write out the top crate of each stack in order, as `report` says.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CraneError::EmptyStack` if any of the stacks is empty and
+    /// `report` has no placeholder for empty stacks.
+    pub fn tops_report(&self, report: &TopsReport) -> Result<String, CraneError<L>> {
+        let mut tops = String::new();
+        for (i, stack) in self.stacks.iter().enumerate() {
+            if i > 0 {
+                tops.push_str(&report.separator);
+            }
+            match (stack.top(), &report.empty) {
+                (Some(top), _) => tops.push_str(&top.to_string()),
+                (None, Some(placeholder)) => tops.push_str(placeholder),
+                (None, None) => return Err(CraneError::EmptyStack { stack: i }),
+            }
+        }
+        Ok(tops)
+    }
+}
+
+/// How `Stacks::tops_report` writes out the top crates. By default the
+/// labels are written one after another and an empty stack is an error,
+/// which is what `tops_string` does.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TopsReport {
+    separator: String,
+    empty: Option<String>,
+}
+
+impl TopsReport {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            separator: String::new(),
+            empty: None,
+        }
+    }
+
+    /// Put `separator` between the labels, which makes multi-character
+    /// labels readable.
+    #[must_use]
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Write `placeholder` for an empty stack instead of failing.
+    #[must_use]
+    pub fn empty(mut self, placeholder: impl Into<String>) -> Self {
+        self.empty = Some(placeholder.into());
+        self
+    }
+}
+
+impl<L> From<Vec<Stack<L>>> for Stacks<L> {
+    fn from(stacks: Vec<Stack<L>>) -> Self {
+        Self {
+            stacks,
+            capacity: Capacity::default(),
+        }
+    }
+}
+
+impl<L> FromIterator<Stack<L>> for Stacks<L> {
+    fn from_iter<T: IntoIterator<Item = Stack<L>>>(iter: T) -> Self {
+        Self {
+            stacks: iter.into_iter().collect(),
+            capacity: Capacity::default(),
+        }
+    }
+}
+
+// This prints the stacks in the simplified input format, one stack per
+// line, so the output of `Display` can be parsed back into a `Stacks`.
+// Any capacity limits come first.
+impl<L: Display> Display for Stacks<L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.capacity)?;
+        for (i, stack) in self.stacks.iter().enumerate() {
+            write!(f, "{}", i + 1)?;
+            if !stack.is_empty() {
+                write!(f, " {stack}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single stack of crates, stored from the bottom of the stack to the top.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stack<L = char> {
+    pub(crate) stack: Vec<L>,
+}
+
+impl<L> Default for Stack<L> {
+    fn default() -> Self {
+        Self { stack: Vec::new() }
+    }
+}
+
+impl<L> Stack<L> {
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// The crate on the top of the stack, if the stack isn't empty.
+    #[must_use]
+    pub fn top(&self) -> Option<&L> {
+        self.stack.last()
+    }
+
+    /// Iterate over the crates from the bottom of the stack to the top.
+    pub fn iter(&self) -> impl Iterator<Item = &L> {
+        self.stack.iter()
+    }
+
+    /// Remove the top `n` crates, returning them in bottom-to-top order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack holds fewer than `n` crates.
+    pub fn take_top(&mut self, n: usize) -> Vec<L> {
+        self.stack.split_off(self.stack.len() - n)
+    }
+}
+
+// Crates are pushed onto the top of the stack in iteration order.
+impl<L> Extend<L> for Stack<L> {
+    fn extend<T: IntoIterator<Item = L>>(&mut self, iter: T) {
+        self.stack.extend(iter);
+    }
+}
+
+impl<L> From<Vec<L>> for Stack<L> {
+    fn from(stack: Vec<L>) -> Self {
+        Self { stack }
+    }
+}
+
+// The crates from bottom to top, separated by spaces, as in the
+// simplified input format.
+impl<L: Display> Display for Stack<L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, c) in self.stack.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
+
+// Implementing `PartialEq<Vec<char>> for Stack` here allows us to
+// say things like `vec!['A', 'B', 'C'] == stack`. This is useful
+// for testing, where we might want to compare a `Stack` to a `Vec<char>`
+// using something like ``assert_eq!(stack, vec!['A', 'B', 'C'])`.
+impl<L: PartialEq> PartialEq<Vec<L>> for Stack<L> {
+    fn eq(&self, other: &Vec<L>) -> bool {
+        self.stack == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stacks_with(contents: &[&[char]]) -> Stacks {
+        contents
+            .iter()
+            .map(|crates| Stack::from(crates.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tops_string_with_empty_stack() {
+        let stacks = stacks_with(&[&['A'], &[], &['B']]);
+        assert_eq!(
+            Err(CraneError::EmptyStack { stack: 1 }),
+            stacks.tops_string()
+        );
+    }
+
+    #[test]
+    fn test_tops_string() {
+        let stacks = stacks_with(&[&['A', 'B'], &['C']]);
+        assert_eq!("BC", stacks.tops_string().unwrap());
+    }
+
+    #[test]
+    fn test_tops_string_with_many_stacks() {
+        let stacks: Stacks = (0..300).map(|_| Stack::from(vec!['X'])).collect();
+        assert_eq!("X".repeat(300), stacks.tops_string().unwrap());
+    }
+
+    #[test]
+    fn test_tops_report() {
+        let stacks = stacks_with(&[&['A', 'B'], &[], &['C']]);
+        let report = TopsReport::new().separator(",").empty("-");
+        assert_eq!("B,-,C", stacks.tops_report(&report).unwrap());
+        assert_eq!(
+            Err(CraneError::EmptyStack { stack: 1 }),
+            stacks.tops_report(&TopsReport::new().separator(","))
+        );
+
+        let skus: Stacks<String> = vec![
+            Stack::from(vec!["AB12".to_string()]),
+            Stack::from(vec!["CD3".to_string(), "EF45".to_string()]),
+        ]
+        .into();
+        assert_eq!("AB12EF45", skus.tops_string().unwrap());
+        assert_eq!(
+            "AB12 EF45",
+            skus.tops_report(&TopsReport::new().separator(" ")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_labels_from_tokens() {
+        assert_eq!(Some('A'), char::from_token("A"));
+        assert_eq!(None, char::from_token("AB"));
+        assert_eq!(None, char::from_token("]"));
+        assert_eq!(Some("AB12".to_string()), String::from_token("AB12"));
+        assert_eq!(None, String::from_token("[AB]"));
+        assert_eq!(Some(1234), u32::from_token("1234"));
+        assert_eq!(None, u64::from_token("12a"));
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        let stacks = stacks_with(&[&['Z', 'N'], &[], &['M', 'C', 'D']]);
+        let reparsed: Stacks = stacks.to_string().parse().unwrap();
+        assert_eq!(stacks, reparsed);
+    }
+}
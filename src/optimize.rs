@@ -0,0 +1,183 @@
+//! Shortening a list of instructions without changing what it does.
+//!
+//! Generated and hand-written plans often waste moves: a group moved
+//! somewhere and straight back, moves that don't change anything, or a
+//! run of moves the crane could have done in one go. The peephole
+//! optimizer in [`CraneInstructions::optimized`] looks at each instruction
+//! together with the one before it and rewrites pairs like that, using
+//! what the [`Crane`] says about its own moves (`inverse`, `is_no_op`, and
+//! `merge`), so the result depends on the crane.
+
+use crate::{
+    crane::{Crane, CraneInstructions},
+    error::PlanError,
+    model::Stacks,
+    plan::Goal,
+};
+
+impl CraneInstructions {
+    /// An equivalent, usually shorter, list of instructions for `crane`.
+    ///
+    /// Instructions the crane says are no-ops are dropped, an instruction
+    /// followed by its inverse cancels out, and pairs of instructions the
+    /// crane can merge are replaced by the merged one. Each rewrite is
+    /// checked against the instruction before it again, so whole chains
+    /// collapse.
+    #[must_use]
+    pub fn optimized<C: Crane + ?Sized>(&self, crane: &C) -> Self {
+        let mut instructions = Vec::with_capacity(self.len());
+        for instruction in self.iter() {
+            let mut pending = Some(*instruction);
+            while let Some(instruction) = pending.take() {
+                if crane.is_no_op(&instruction) {
+                    continue;
+                }
+                let Some(last) = instructions.last() else {
+                    instructions.push(instruction);
+                    continue;
+                };
+                if crane.inverse(last) == Some(instruction) {
+                    instructions.pop();
+                } else if let Some(merged) = crane.merge(last, &instruction) {
+                    instructions.pop();
+                    pending = Some(merged);
+                } else {
+                    instructions.push(instruction);
+                }
+            }
+        }
+        Self { instructions }
+    }
+
+    /// Optimize these instructions for `crane`, and check by simulation
+    /// that the result takes `start` to the same stacks as the original.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PlanError::Replay` if the original instructions can't be
+    /// applied to `start`, and the errors from `Goal::verify` if the
+    /// optimized instructions don't do the same thing, which means one
+    /// of the crane's rewrites is wrong.
+    pub fn optimized_for<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        start: &Stacks,
+    ) -> Result<Self, PlanError> {
+        let end = start
+            .clone()
+            .apply_instructions(crane, self)
+            .map_err(PlanError::Replay)?;
+        let optimized = self.optimized(crane);
+        Goal::Stacks(end).verify(start, crane, &optimized)?;
+        Ok(optimized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        crane::{CraneInstruction, CraneModel, CrateMover9000, CrateMover9001},
+        model::Stack,
+        parse::PuzzleInput,
+    };
+
+    fn instructions(s: &str) -> CraneInstructions {
+        s.parse().unwrap()
+    }
+
+    fn lines(instructions: &CraneInstructions) -> Vec<String> {
+        instructions.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_move_and_back_cancels() {
+        let plan = instructions("move 2 from 1 to 3\nmove 2 from 3 to 1\nmove 1 from 2 to 1");
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            assert_eq!(vec!["move 1 from 2 to 1"], lines(&plan.optimized(&crane)));
+        }
+    }
+
+    #[test]
+    fn test_no_ops_are_dropped() {
+        let plan = instructions("move 0 from 1 to 2\nmove 1 from 2 to 2\nmove 3 from 2 to 2");
+        assert_eq!(
+            vec!["move 3 from 2 to 2"],
+            lines(&plan.optimized(&CrateMover9000))
+        );
+        assert!(plan.optimized(&CrateMover9001).is_empty());
+    }
+
+    #[test]
+    fn test_merges_depend_on_the_crane() {
+        let same_pair = instructions("move 2 from 1 to 2\nmove 1 from 1 to 2");
+        assert_eq!(
+            vec!["move 3 from 1 to 2"],
+            lines(&same_pair.optimized(&CrateMover9000))
+        );
+        assert_eq!(2, same_pair.optimized(&CrateMover9001).len());
+
+        let chain = instructions("move 2 from 1 to 2\nmove 2 from 2 to 3\nmove 2 from 3 to 4");
+        assert_eq!(
+            vec!["move 2 from 1 to 4"],
+            lines(&chain.optimized(&CrateMover9001))
+        );
+        assert_eq!(3, chain.optimized(&CrateMover9000).len());
+    }
+
+    #[test]
+    fn test_rewrites_cascade() {
+        // Merging the middle pair makes the result the inverse of the first.
+        let plan = instructions("move 1 from 1 to 2\nmove 1 from 2 to 3\nmove 1 from 3 to 1");
+        assert!(plan.optimized(&CrateMover9001).is_empty());
+    }
+
+    #[test]
+    fn test_optimized_for_checks_by_simulation() {
+        let contents = fs::read_to_string("input.txt").unwrap();
+        let input: PuzzleInput = contents.parse().unwrap();
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let optimized = input
+                .instructions
+                .optimized_for(&crane, &input.stacks)
+                .unwrap();
+            assert!(optimized.len() <= input.instructions.len());
+        }
+
+        let stacks: Stacks = "1 A\n2".parse().unwrap();
+        let invalid = instructions("move 2 from 1 to 2");
+        assert!(matches!(
+            invalid.optimized_for(&CrateMover9000, &stacks),
+            Err(PlanError::Replay(_))
+        ));
+    }
+
+    // A crane that wrongly claims every move can be merged with the next.
+    struct Careless;
+
+    impl Crane for Careless {
+        fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize) {
+            CrateMover9001.transfer(from, to, n);
+        }
+
+        fn merge(
+            &self,
+            first: &CraneInstruction,
+            _second: &CraneInstruction,
+        ) -> Option<CraneInstruction> {
+            Some(*first)
+        }
+    }
+
+    #[test]
+    fn test_optimized_for_catches_bad_rewrites() {
+        let stacks: Stacks = "1 A B\n2".parse().unwrap();
+        let plan = instructions("move 1 from 1 to 2\nmove 1 from 1 to 2");
+        assert_eq!(
+            Err(PlanError::GoalMissed),
+            plan.optimized_for(&Careless, &stacks)
+        );
+    }
+}
@@ -0,0 +1,1216 @@
+//! Parsing the puzzle input into `Stacks` and `CraneInstructions`.
+//!
+//! The input comes in two parts separated by a blank line: the stack
+//! configuration, followed by the crane instructions, one per line like
+//! `move 1 from 2 to 1`. The stack configuration can either be one stack
+//! per line like `1 Z N` or the original column drawing; see [`StackFormat`].
+//! Either can also contain `capacity` lines limiting the stack heights.
+
+use std::{ops::Range, str::FromStr};
+
+use crate::{
+    capacity::Capacity,
+    crane::{Crane, CraneInstruction, CraneInstructions},
+    error::{CraneError, ParseError, ParseErrorKind},
+    model::{Label, Stack, Stacks},
+};
+
+/// The two layouts a stack configuration can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackFormat {
+    /// The simplified format, one stack per line, bottom crate first:
+    ///
+    /// ```text
+    /// 1 Z N
+    /// 2 M C D
+    /// 3 P
+    /// ```
+    Lines,
+    /// The original Advent of Code drawing, with the stacks drawn as
+    /// columns and a footer line numbering them:
+    ///
+    /// ```text
+    ///     [D]
+    /// [N] [C]
+    /// [Z] [M] [P]
+    ///  1   2   3
+    /// ```
+    Drawing,
+}
+
+impl StackFormat {
+    /// Work out which format a stack configuration is written in.
+    ///
+    /// Crates in a drawing are always in brackets and the simplified format
+    /// never uses brackets, so any line starting with `[` means it's a drawing.
+    #[must_use]
+    pub fn detect(s: &str) -> Self {
+        if s.lines().any(|line| line.trim_start().starts_with('[')) {
+            Self::Drawing
+        } else {
+            Self::Lines
+        }
+    }
+
+    /// Parse a stack configuration that is known to be in this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` if the configuration isn't valid in this format.
+    pub fn parse<L: Label>(self, s: &str) -> Result<Stacks<L>, ParseError> {
+        match self {
+            Self::Lines => parse_lines(s),
+            Self::Drawing => parse_drawing(s),
+        }
+    }
+}
+
+impl<L: Label> FromStr for Stacks<L> {
+    type Err = ParseError;
+
+    // The format is detected automatically, so both `input.txt` and the
+    // original drawing in `original_input.txt` can be parsed directly.
+    // Each whitespace-separated token is one crate, whatever the label type.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StackFormat::detect(s).parse(s)
+    }
+}
+
+/// The largest stack number we accept, so that a typo like `1000000 A`
+/// doesn't quietly allocate a million empty stacks.
+pub const MAX_STACK_ID: usize = 10_000;
+
+// You probably want to use `s.lines()` to create an iterator over the lines (one per stack).
+// Then for each line:
+//   (a) extract the number at the front as the stack number
+//   (b) extract the following characters as the stack contents
+// The function `split_ascii_whitespace()` should prove useful.
+// Note that the stack numbers start at 1 and you'll need the indices
+// in `Stacks::stacks` to start at 0. The number of stacks is the highest
+// stack number seen; any numbers that are skipped are empty stacks.
+fn parse_lines<L: Label>(s: &str) -> Result<Stacks<L>, ParseError> {
+    // create a new Stacks struct
+    let mut stacks = Stacks::default();
+    // the line each stack was defined on, so we can report duplicates
+    let mut defined_on: Vec<Option<usize>> = Vec::new();
+    let mut capacities = CapacityLines::default();
+
+    // for each line in the input string, parse the stack number and stack contents
+    for (line_num, line) in (1..).zip(s.lines()) {
+        if capacities.read(line, line_num)? {
+            continue;
+        }
+        let mut parts = tokens(line).into_iter(); // split the line into parts
+        let Some((span, id)) = parts.next() else {
+            return Err(ParseError::new(
+                ParseErrorKind::MissingStackId,
+                line_num,
+                0..0,
+                "",
+            ));
+        };
+        let stack_num = parse_stack_id(id, line_num, span.clone())?;
+        if stack_num >= stacks.stacks.len() {
+            // grow the yard so that this stack number exists
+            stacks.stacks.resize_with(stack_num + 1, Stack::default);
+            defined_on.resize(stack_num + 1, None);
+        }
+        if let Some(first_line) = defined_on[stack_num] {
+            return Err(ParseError::new(
+                ParseErrorKind::DuplicateStackId { first_line },
+                line_num,
+                span,
+                id,
+            ));
+        }
+        defined_on[stack_num] = Some(line_num);
+
+        // get the stack contents, one crate per part
+        let crates = parts
+            .map(|(span, label)| parse_crate_label(label, line_num, span))
+            .collect::<Result<Vec<L>, ParseError>>()?;
+        stacks.stacks[stack_num] = Stack::from(crates); // set the stack contents
+    }
+    capacities.apply_to(&mut stacks)?;
+    Ok(stacks)
+}
+
+fn parse_drawing<L: Label>(s: &str) -> Result<Stacks<L>, ParseError> {
+    let mut capacities = CapacityLines::default();
+    let mut rows: Vec<(usize, Vec<char>)> = Vec::new();
+    for (line_num, line) in (1..).zip(s.lines()) {
+        if !line.trim().is_empty() && !capacities.read(line, line_num)? {
+            rows.push((line_num, line.chars().collect()));
+        }
+    }
+
+    // The footer tells us where each stack's column is. Without one we
+    // fall back to the standard layout of four characters per stack.
+    let columns = match rows.last() {
+        Some((line_num, footer)) if !footer.contains(&'[') => {
+            let columns = footer_columns(*line_num, footer)?;
+            rows.pop();
+            columns
+        }
+        _ => {
+            let width = rows.iter().map(|(_, row)| row.len()).max().unwrap_or(0);
+            (0..width.div_ceil(4))
+                .map(|pos| Some(1 + 4 * pos))
+                .collect()
+        }
+    };
+
+    for (line_num, row) in &rows {
+        check_row::<L>(*line_num, row, &columns)?;
+    }
+
+    let mut stacks = rows
+        .iter()
+        // We reverse the rows because we want the "bottom" rows
+        // to be pushed onto the stacks first so those values end
+        // up on the bottom of the stacks.
+        .rev()
+        // Convert each row to the elements at each level, with spaces
+        // for stacks that don't have anything at that level.
+        .map(|(_, row)| extract_stack_elements(row, &columns))
+        // "Loop" over each row/level, pushing the non-space values onto
+        // the appropriate stacks.
+        .fold(Stacks::with_len(columns.len()), push_values_on_stacks);
+    capacities.apply_to(&mut stacks)?;
+    Ok(stacks)
+}
+
+/// The `capacity` lines of a stack configuration, along with where each
+/// limit was set so we can report duplicates and stacks that start over
+/// their limit.
+#[derive(Default)]
+struct CapacityLines {
+    capacity: Capacity,
+    all_on: Option<CapacityLine>,
+    stack_on: Vec<Option<CapacityLine>>,
+}
+
+#[derive(Clone)]
+struct CapacityLine {
+    line_num: usize,
+    span: Range<usize>,
+    text: String,
+}
+
+impl CapacityLines {
+    /// Read `line` if it's `capacity N` or `capacity N for S`, returning
+    /// whether it was a capacity line at all.
+    fn read(&mut self, line: &str, line_num: usize) -> Result<bool, ParseError> {
+        let parts = tokens(line);
+        let (Some((first, "capacity")), Some((last, _))) = (parts.first(), parts.last()) else {
+            return Ok(false);
+        };
+        let span = first.start..last.end;
+        let text = line.trim();
+        let invalid = |span: &Range<usize>, text: &str| {
+            ParseError::new(
+                ParseErrorKind::InvalidCapacity,
+                line_num,
+                span.clone(),
+                text,
+            )
+        };
+
+        let (limit, stack) = match &parts[1..] {
+            [limit] => (limit, None),
+            [limit, (_, "for"), (stack_span, stack)] => (
+                limit,
+                Some(parse_stack_id(stack, line_num, stack_span.clone())?),
+            ),
+            _ => return Err(invalid(&span, text)),
+        };
+        let (limit_span, limit) = limit;
+        let limit: usize = limit.parse().map_err(|_| invalid(limit_span, limit))?;
+
+        let first_line = match stack {
+            None => &mut self.all_on,
+            Some(stack) => {
+                if stack >= self.stack_on.len() {
+                    self.stack_on.resize(stack + 1, None);
+                }
+                &mut self.stack_on[stack]
+            }
+        };
+        if let Some(first) = first_line {
+            return Err(ParseError::new(
+                ParseErrorKind::DuplicateCapacity {
+                    first_line: first.line_num,
+                },
+                line_num,
+                span,
+                text,
+            ));
+        }
+        *first_line = Some(CapacityLine {
+            line_num,
+            span,
+            text: text.to_string(),
+        });
+        match stack {
+            None => self.capacity.all = Some(limit),
+            Some(stack) => self.capacity.set_limit(stack, limit),
+        }
+        Ok(true)
+    }
+
+    // As with stack lines, a capacity for a stack past the last one
+    // defines the stacks up to it. A stack that starts with more crates
+    // than its capacity is an error on the line that set the capacity.
+    fn apply_to<L>(self, stacks: &mut Stacks<L>) -> Result<(), ParseError> {
+        if self.capacity.per_stack.len() > stacks.stacks.len() {
+            stacks
+                .stacks
+                .resize_with(self.capacity.per_stack.len(), Stack::default);
+        }
+        for (stack, crates) in stacks.stacks.iter().enumerate() {
+            let Some(capacity) = self.capacity.limit(stack) else {
+                continue;
+            };
+            if crates.len() > capacity {
+                let set_on = self.stack_on.get(stack).and_then(Option::as_ref);
+                // A limit always comes from a capacity line.
+                let Some(line) = set_on.or(self.all_on.as_ref()) else {
+                    continue;
+                };
+                return Err(ParseError::new(
+                    ParseErrorKind::OverCapacity {
+                        stack,
+                        height: crates.len(),
+                    },
+                    line.line_num,
+                    line.span.clone(),
+                    &line.text,
+                ));
+            }
+        }
+        stacks.capacity = self.capacity;
+        Ok(())
+    }
+}
+
+// The position of each stack's crates in a drawing row is the position of
+// the last digit of that stack's number in the footer, so both ` 1   2`
+// and right-aligned numbers like `  9  10` line up with their columns.
+// The result is indexed by stack, with `None` for any stack numbers that
+// are skipped in the footer.
+fn footer_columns(line_num: usize, footer: &[char]) -> Result<Vec<Option<usize>>, ParseError> {
+    let mut columns = Vec::new();
+    for (span, id) in char_tokens(footer) {
+        let text = id.iter().collect::<String>();
+        let stack_num = parse_stack_id(&text, line_num, span.clone())?;
+        if stack_num >= columns.len() {
+            columns.resize(stack_num + 1, None);
+        }
+        if columns[stack_num].is_some() {
+            return Err(ParseError::new(
+                ParseErrorKind::DuplicateStackId {
+                    first_line: line_num,
+                },
+                line_num,
+                span,
+                &text,
+            ));
+        }
+        columns[stack_num] = Some(span.end - 1);
+    }
+    Ok(columns)
+}
+
+// Every non-blank part of a row has to be a single crate like `[A]`
+// sitting directly above one of the stack numbers.
+fn check_row<L: Label>(
+    line_num: usize,
+    row: &[char],
+    columns: &[Option<usize>],
+) -> Result<(), ParseError> {
+    for (span, cell) in char_tokens(row) {
+        let text = cell.iter().collect::<String>();
+        let label = match cell {
+            ['[', label, ']'] => *label,
+            _ => '[',
+        };
+        parse_crate_label::<L>(&label.to_string(), line_num, span.clone()).map_err(|error| {
+            ParseError {
+                text: text.clone(),
+                ..error
+            }
+        })?;
+        if !columns.contains(&Some(span.start + 1)) {
+            return Err(ParseError::new(
+                ParseErrorKind::MisalignedCrate,
+                line_num,
+                span,
+                &text,
+            ));
+        }
+    }
+    Ok(())
+}
+
+// An alternative approach would be to just do `line.chars()` and
+// `line.nth(4)` for each stack in the mapping. That would avoid
+// creating the `Vec<char>` for `line` that we have at the moment.
+// We'd probably have to special case the first stack, though, and
+// that would be ugly, so I'm not sure that wins.
+fn extract_stack_elements(row: &[char], columns: &[Option<usize>]) -> Vec<char> {
+    columns
+        .iter()
+        .map(|pos| pos.and_then(|pos| row.get(pos).copied()).unwrap_or(' '))
+        .collect()
+}
+
+// Note that the argument here is `stacks` and not `&mut stacks` because we
+// take ownership of this `Stacks` value so we can mutate it in the
+// `fold()` call in `parse_drawing`. `check_row` has already made sure
+// every crate is a valid label.
+fn push_values_on_stacks<L: Label>(mut stacks: Stacks<L>, level: Vec<char>) -> Stacks<L> {
+    for (stack, c) in stacks.stacks.iter_mut().zip(level) {
+        if c != ' ' {
+            stack.extend(L::from_token(&c.to_string()));
+        }
+    }
+    stacks
+}
+
+/// Turn a stack number from the input into an index into `Stacks`.
+fn parse_stack_id(id: &str, line: usize, span: Range<usize>) -> Result<usize, ParseError> {
+    let error = |kind| ParseError::new(kind, line, span.clone(), id);
+    match id.parse::<usize>() {
+        Err(_) if id.parse::<i64>().is_ok_and(i64::is_negative) => {
+            Err(error(ParseErrorKind::NegativeStackId))
+        }
+        Err(e) => Err(error(ParseErrorKind::StackIdParseError(e))),
+        Ok(0) => Err(error(ParseErrorKind::ZeroStackId)),
+        Ok(n) if n > MAX_STACK_ID => Err(error(ParseErrorKind::StackIdOutOfRange {
+            max: MAX_STACK_ID,
+        })),
+        Ok(n) => Ok(n - 1),
+    }
+}
+
+/// Read one token as a crate label, pointing at it if it isn't valid.
+fn parse_crate_label<L: Label>(
+    label: &str,
+    line: usize,
+    span: Range<usize>,
+) -> Result<L, ParseError> {
+    L::from_token(label).ok_or_else(|| {
+        ParseError::new(
+            ParseErrorKind::IllegalCrateLabel {
+                expected: L::EXPECTED,
+            },
+            line,
+            span,
+            label,
+        )
+    })
+}
+
+/// Split a line into whitespace separated tokens, along with the columns
+/// (counted in characters) that each token covers.
+fn tokens(line: &str) -> Vec<(Range<usize>, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (column, (offset, c)) in line.char_indices().chain([(line.len(), ' ')]).enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column, offset)),
+            (Some((start_column, start_offset)), true) => {
+                tokens.push((start_column..column, &line[start_offset..offset]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// The same as `tokens`, but for a line that's already been split into characters.
+fn char_tokens(row: &[char]) -> Vec<(Range<usize>, &[char])> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (column, c) in row.iter().chain([&' ']).enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(column),
+            (Some(start_column), true) => {
+                tokens.push((start_column..column, &row[start_column..column]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+// A single stack is written like a stack line without its number: the
+// crate labels from the bottom up, separated by whitespace.
+impl<L: Label> FromStr for Stack<L> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let crates = tokens(s)
+            .into_iter()
+            .map(|(span, label)| parse_crate_label(label, 1, span))
+            .collect::<Result<Vec<L>, ParseError>>()?;
+        Ok(Self::from(crates))
+    }
+}
+
+/// How strictly to parse crane instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Instructions must be exactly `move N from A to B`.
+    #[default]
+    Strict,
+    /// Any line containing exactly three numbers is read as `N`, `A`, and
+    /// `B`, whatever the words around them are.
+    Lenient,
+}
+
+impl CraneInstruction {
+    /// Parse a single instruction line in the given mode. Errors are
+    /// reported as being on line 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` pointing at the first token that doesn't fit.
+    pub fn parse_with(s: &str, mode: ParseMode) -> Result<Self, ParseError> {
+        parse_instruction(s, mode).map(|parsed| parsed.instruction)
+    }
+}
+
+/// An instruction along with the columns its two stack numbers were in,
+/// so we can point at them if they turn out not to exist.
+struct ParsedInstruction {
+    instruction: CraneInstruction,
+    stack_spans: [Range<usize>; 2],
+}
+
+fn parse_instruction(s: &str, mode: ParseMode) -> Result<ParsedInstruction, ParseError> {
+    match mode {
+        ParseMode::Strict => parse_instruction_strict(s),
+        ParseMode::Lenient => parse_instruction_lenient(s),
+    }
+}
+
+impl FromStr for CraneInstruction {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, ParseMode::Strict)
+    }
+}
+
+// The instruction specification lines have the form
+//     move 13 from 8 to 7
+// so we walk through the tokens in order, checking each keyword and
+// parsing each number, and complain about the first one that's wrong.
+fn parse_instruction_strict(s: &str) -> Result<ParsedInstruction, ParseError> {
+    let mut parts = InstructionTokens {
+        parts: tokens(s).into_iter(),
+        end: s.chars().count(),
+    };
+
+    parts.keyword("move")?;
+    let (span, count) = parts.next("a number of crates")?;
+    let num_to_move = count
+        .parse()
+        .map_err(|e| ParseError::new(ParseErrorKind::CrateCountParseError(e), 1, span, count))?;
+    parts.keyword("from")?;
+    let (from_span, from) = parts.next("a stack number")?;
+    let from_stack = parse_stack_id(from, 1, from_span.clone())?;
+    parts.keyword("to")?;
+    let (to_span, to) = parts.next("a stack number")?;
+    let to_stack = parse_stack_id(to, 1, to_span.clone())?;
+    parts.finish()?;
+
+    Ok(ParsedInstruction {
+        instruction: CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        },
+        stack_spans: [from_span, to_span],
+    })
+}
+
+/// The tokens of an instruction line, consumed in order by the strict parser.
+struct InstructionTokens<'a> {
+    parts: std::vec::IntoIter<(Range<usize>, &'a str)>,
+    // Missing tokens are reported at the end of the line.
+    end: usize,
+}
+
+impl<'a> InstructionTokens<'a> {
+    fn next(&mut self, expected: &'static str) -> Result<(Range<usize>, &'a str), ParseError> {
+        self.parts.next().ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::MissingToken { expected },
+                1,
+                self.end..self.end,
+                "",
+            )
+        })
+    }
+
+    fn keyword(&mut self, expected: &'static str) -> Result<(), ParseError> {
+        let (span, token) = self.next(expected)?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(ParseError::new(
+                ParseErrorKind::UnexpectedToken { expected },
+                1,
+                span,
+                token,
+            ))
+        }
+    }
+
+    fn finish(mut self) -> Result<(), ParseError> {
+        match self.parts.next() {
+            Some((span, extra)) => Err(ParseError::new(
+                ParseErrorKind::UnexpectedToken {
+                    expected: "the end of the line",
+                },
+                1,
+                span,
+                extra,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+// All we need to capture are the three numbers, which happen to
+// be in the odd positions in the input line. I used a `filter` statement
+// to extract those three items from the list, which I could
+// then parse into `usize` using a `map` statement. This is the original
+// parser, which is happy with any words around the numbers.
+fn parse_instruction_lenient(s: &str) -> Result<ParsedInstruction, ParseError> {
+    let parts: Vec<(Range<usize>, &str, usize)> = tokens(s)
+        .into_iter()
+        .filter_map(|(span, part)| part.parse().ok().map(|n| (span, part, n)))
+        .collect();
+
+    let [(_, _, num_to_move), (from_span, from, _), (to_span, to, _)] = &parts[..] else {
+        let text = s.trim();
+        let start = s.chars().take_while(|c| c.is_whitespace()).count();
+        let span = start..start + text.chars().count();
+        return Err(ParseError::new(
+            ParseErrorKind::InvalidInstruction,
+            1,
+            span,
+            text,
+        ));
+    };
+
+    Ok(ParsedInstruction {
+        instruction: CraneInstruction {
+            num_to_move: *num_to_move,
+            from_stack: parse_stack_id(from, 1, from_span.clone())?,
+            to_stack: parse_stack_id(to, 1, to_span.clone())?,
+        },
+        stack_spans: [from_span.clone(), to_span.clone()],
+    })
+}
+
+impl CraneInstructions {
+    /// Parse a list of instructions, one per line, in the given mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `ParseError` for the first line that isn't a valid instruction.
+    pub fn parse_with(s: &str, mode: ParseMode) -> Result<Self, ParseError> {
+        let instructions = (1..)
+            .zip(s.lines())
+            .map(|(line_num, line)| {
+                CraneInstruction::parse_with(line, mode).map_err(|error| error.on_line(line_num))
+            })
+            .collect::<Result<Vec<CraneInstruction>, ParseError>>()?;
+        Ok(Self { instructions })
+    }
+}
+
+/// Everything we learned from parsing an instruction list without stopping
+/// at the first bad line: the instructions that did parse, and an error for
+/// each line that didn't.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InstructionReport {
+    /// The instructions from the lines that parsed, in order.
+    pub instructions: CraneInstructions,
+    /// The line number of each of those instructions, starting at 1.
+    pub lines: Vec<usize>,
+    /// One error per malformed line, in line order.
+    pub errors: Vec<ParseError>,
+}
+
+impl InstructionReport {
+    #[must_use]
+    pub const fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The instructions if every line parsed, or all of the errors if not.
+    ///
+    /// # Errors
+    ///
+    /// Returns the collected `ParseError`s if there were any.
+    pub fn into_result(self) -> Result<CraneInstructions, Vec<ParseError>> {
+        if self.errors.is_empty() {
+            Ok(self.instructions)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+impl CraneInstructions {
+    /// Parse every line of an instruction list, reporting all of the
+    /// malformed lines at once instead of stopping at the first one.
+    #[must_use]
+    pub fn parse_all(s: &str, mode: ParseMode) -> InstructionReport {
+        parse_instruction_lines(s, mode, 1, None)
+    }
+}
+
+// Parse each line of `s` as an instruction, numbering the lines from
+// `first_line`. If we know how many stacks there are, instructions
+// that use stacks past the end are errors as well.
+fn parse_instruction_lines(
+    s: &str,
+    mode: ParseMode,
+    first_line: usize,
+    num_stacks: Option<usize>,
+) -> InstructionReport {
+    let mut report = InstructionReport::default();
+    for (line_num, line) in (first_line..).zip(s.lines()) {
+        match parse_instruction_line(line, mode, line_num, num_stacks) {
+            Ok(instruction) => {
+                report.instructions.instructions.push(instruction);
+                report.lines.push(line_num);
+            }
+            Err(error) => report.errors.push(error),
+        }
+    }
+    report
+}
+
+/// Parse the instruction on line `line_num` of an input, checking that
+/// its stacks exist if we know how many there are.
+pub(crate) fn parse_instruction_line(
+    line: &str,
+    mode: ParseMode,
+    line_num: usize,
+    num_stacks: Option<usize>,
+) -> Result<CraneInstruction, ParseError> {
+    parse_instruction(line, mode)
+        .and_then(|parsed| {
+            check_stacks_exist(line, &parsed, num_stacks)?;
+            Ok(parsed.instruction)
+        })
+        .map_err(|error| error.on_line(line_num))
+}
+
+fn check_stacks_exist(
+    line: &str,
+    parsed: &ParsedInstruction,
+    num_stacks: Option<usize>,
+) -> Result<(), ParseError> {
+    let Some(num_stacks) = num_stacks else {
+        return Ok(());
+    };
+    let stacks = [parsed.instruction.from_stack, parsed.instruction.to_stack];
+    for (stack, span) in stacks.into_iter().zip(&parsed.stack_spans) {
+        if stack >= num_stacks {
+            let text: String = line.chars().skip(span.start).take(span.len()).collect();
+            return Err(ParseError::new(
+                ParseErrorKind::UnknownStack { num_stacks },
+                1,
+                span.clone(),
+                &text,
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl FromStr for CraneInstructions {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, ParseMode::Strict)
+    }
+}
+
+/// A whole puzzle input: the stack configuration, a blank line, and then
+/// the crane instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleInput<L = char> {
+    pub stacks: Stacks<L>,
+    pub instructions: CraneInstructions,
+    /// The line of the input that the first instruction is on, starting at 1.
+    pub first_instruction_line: usize,
+}
+
+impl<L: Label> PuzzleInput<L> {
+    /// Parse a whole input file in the given mode.
+    ///
+    /// Unlike parsing the two parts separately, the line numbers in the
+    /// errors are lines of the whole input, instructions that use stacks
+    /// the configuration doesn't define are errors, and every malformed
+    /// instruction line is reported rather than just the first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error in the stack configuration if there is one, or
+    /// else all of the errors in the instructions.
+    pub fn parse_with(s: &str, mode: ParseMode) -> Result<Self, Vec<ParseError>> {
+        let Some((stack_config, instructions)) = split_at_blank_line(s) else {
+            let line = s.lines().count() + 1;
+            return Err(vec![ParseError::new(
+                ParseErrorKind::MissingBlankLine,
+                line,
+                0..0,
+                "",
+            )]);
+        };
+        let stacks: Stacks<L> = stack_config.parse().map_err(|error| vec![error])?;
+        // The instructions start after the stack configuration and the blank line.
+        let first_instruction_line = stack_config.lines().count() + 2;
+        let report = parse_instruction_lines(
+            instructions,
+            mode,
+            first_instruction_line,
+            Some(stacks.len()),
+        );
+        Ok(Self {
+            stacks,
+            instructions: report.into_result()?,
+            first_instruction_line,
+        })
+    }
+
+    /// The line of the input that the instruction with this index came from.
+    #[must_use]
+    pub const fn instruction_line(&self, index: usize) -> usize {
+        self.first_instruction_line + index
+    }
+
+    /// Apply all the instructions to a copy of the stacks.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Stacks::apply_instructions`, with the
+    /// input line of the failed instruction filled in.
+    pub fn run<C: Crane + ?Sized>(&self, crane: &C) -> Result<Stacks<L>, CraneError<L>> {
+        self.stacks
+            .clone()
+            .apply_instructions(crane, &self.instructions)
+            .map_err(|error| self.locate_error(error))
+    }
+
+    /// Fill in the input line of the failed instruction, for an error
+    /// from applying this input's instructions some other way than `run`.
+    #[must_use]
+    pub fn locate_error(&self, error: CraneError<L>) -> CraneError<L> {
+        match &error {
+            CraneError::Instruction(failure) => {
+                let line = self.instruction_line(failure.index);
+                error.on_line(line)
+            }
+            _ => error,
+        }
+    }
+}
+
+/// Split `s` around its first blank line, splitting lines the way
+/// `str::lines` does so that `\r\n` line endings work too.
+fn split_at_blank_line(s: &str) -> Option<(&str, &str)> {
+    let mut start = 0;
+    for line in s.split_inclusive('\n') {
+        let end = start + line.len();
+        if line == "\n" || line == "\r\n" {
+            // Leave off the line ending before the blank line.
+            let config = &s[..start];
+            let config = config.strip_suffix('\n').unwrap_or(config);
+            return Some((config.strip_suffix('\r').unwrap_or(config), &s[end..]));
+        }
+        start = end;
+    }
+    None
+}
+
+impl<L: Label> FromStr for PuzzleInput<L> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, ParseMode::Strict).map_err(|mut errors| errors.swap_remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::crane::CrateMover9001;
+
+    // Test that we can parse stacks correctly.
+    #[test]
+    fn test_from_str() {
+        // The `\` at the end of the line escapes the newline and all following whitespace.
+        let input = "1 Z N\n\
+                           2 M C D\n\
+                           3 P";
+        let stacks: Stacks = input.parse().unwrap();
+        assert_eq!(2, stacks.stacks[0].len());
+        // The implementation of `PartialEq<Vec<char>>` in `model` is what allows
+        // us to compare a `Stack` to a `Vec<char>` here and in other tests.
+        assert_eq!(stacks.stacks[0], vec!['Z', 'N']);
+        assert_eq!(3, stacks.stacks[1].len());
+        assert_eq!(stacks.stacks[1], vec!['M', 'C', 'D']);
+        assert_eq!(1, stacks.stacks[2].len());
+        assert_eq!(stacks.stacks[2], vec!['P']);
+    }
+
+    #[test]
+    fn test_stack_from_str() {
+        let stack: Stack = "A B  C".parse().unwrap();
+        assert_eq!(stack, vec!['A', 'B', 'C']);
+        let stack: Stack<String> = "SKU-1 SKU-2".parse().unwrap();
+        assert_eq!(2, stack.len());
+        assert!("".parse::<Stack>().unwrap().is_empty());
+
+        let error = "A BC".parse::<Stack>().unwrap_err();
+        assert_eq!((2..4, "BC".to_string()), (error.span, error.text));
+    }
+
+    #[test]
+    fn test_puzzle_input_crlf() {
+        let lf = "1 A B\n2 C\n\nmove 1 from 1 to 2\nmove 1 from 2 to 1\n";
+        let crlf = lf.replace('\n', "\r\n");
+        let expected: PuzzleInput = lf.parse().unwrap();
+        let input: PuzzleInput = crlf.parse().unwrap();
+        assert_eq!(expected.stacks, input.stacks);
+        assert_eq!(expected.instructions, input.instructions);
+        assert_eq!(4, input.first_instruction_line);
+
+        let error = "1 A\r\n2 B\r\n".parse::<PuzzleInput>().unwrap_err();
+        assert_eq!(
+            (ParseErrorKind::MissingBlankLine, 3),
+            (error.kind, error.line)
+        );
+    }
+
+    #[test]
+    fn test_from_str_sizes_from_input() {
+        let stacks: Stacks = "1 A\n10 B C\n".parse().unwrap();
+        assert_eq!(10, stacks.len());
+        assert_eq!(stacks.stacks[9], vec!['B', 'C']);
+        assert!(stacks.stacks[4].is_empty());
+
+        let stacks: Stacks = "1 A\n2 B\n3 C".parse().unwrap();
+        assert_eq!(3, stacks.len());
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(StackFormat::Lines, StackFormat::detect("1 Z N\n2 M C D"));
+        assert_eq!(
+            StackFormat::Drawing,
+            StackFormat::detect("    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3")
+        );
+    }
+
+    #[test]
+    fn test_from_str_drawing() {
+        let drawing = "    [D]    \n\
+                       [N] [C]    \n\
+                       [Z] [M] [P]\n \
+                        1   2   3 ";
+        let stacks: Stacks = drawing.parse().unwrap();
+        let lines: Stacks = "1 Z N\n2 M C D\n3 P".parse().unwrap();
+        assert_eq!(lines, stacks);
+    }
+
+    #[test]
+    fn test_from_str_drawing_with_trailing_empty_stacks() {
+        // Trailing spaces are often trimmed, so the footer decides how many stacks there are.
+        let stacks: Stacks = "[A]\n 1   2   3".parse().unwrap();
+        assert_eq!(3, stacks.len());
+        assert_eq!(stacks.stacks[0], vec!['A']);
+        assert!(stacks.stacks[2].is_empty());
+    }
+
+    #[test]
+    fn test_from_str_drawing_without_footer() {
+        let stacks: Stacks = "    [D]\n[N] [C]".parse().unwrap();
+        assert_eq!(2, stacks.len());
+        assert_eq!(stacks.stacks[1], vec!['C', 'D']);
+    }
+
+    #[test]
+    fn test_drawing_and_lines_inputs_agree() {
+        let drawing = fs::read_to_string("original_input.txt").unwrap();
+        let lines = fs::read_to_string("input.txt").unwrap();
+        let (drawing, _) = drawing.split_once("\n\n").unwrap();
+        let (lines, _) = lines.split_once("\n\n").unwrap();
+        assert_eq!(
+            lines.parse::<Stacks>().unwrap(),
+            drawing.parse::<Stacks>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_str_word_and_number_labels() {
+        let input: PuzzleInput<String> = "1 AB12 CD3\n2 EF45\n\nmove 2 from 1 to 2\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            input.stacks.stacks[0],
+            vec!["AB12".to_string(), "CD3".to_string()]
+        );
+        let stacks = input.run(&CrateMover9001).unwrap();
+        assert_eq!(Some(&"CD3".to_string()), stacks.stacks[1].top());
+        assert_eq!(stacks, stacks.to_string().parse().unwrap());
+
+        let drawing: Stacks<u32> = "[1]\n[2] [3]\n 1   2".parse().unwrap();
+        assert_eq!(drawing.stacks[0], vec![2, 1]);
+
+        let error = "1 7 x".parse::<Stacks<u32>>().unwrap_err();
+        assert_eq!(
+            ParseErrorKind::IllegalCrateLabel {
+                expected: "a number"
+            },
+            error.kind
+        );
+        assert_eq!((4..5, "x"), (error.span, error.text.as_str()));
+    }
+
+    fn parse_error(input: &str) -> ParseError {
+        input.parse::<Stacks>().unwrap_err()
+    }
+
+    #[test]
+    fn test_from_str_errors() {
+        let error = parse_error("1 A\n\n2 B");
+        assert_eq!(
+            (ParseErrorKind::MissingStackId, 2),
+            (error.kind, error.line)
+        );
+
+        let error = parse_error("1 A\nx B");
+        assert!(matches!(error.kind, ParseErrorKind::StackIdParseError(_)));
+        assert_eq!(
+            (2, 0..1, "x"),
+            (error.line, error.span, error.text.as_str())
+        );
+
+        let error = parse_error("0 A");
+        assert_eq!(ParseErrorKind::ZeroStackId, error.kind);
+
+        let error = parse_error("1 A\n2 B\n 1 C");
+        assert_eq!(
+            ParseErrorKind::DuplicateStackId { first_line: 1 },
+            error.kind
+        );
+        assert_eq!((3, 1..2), (error.line, error.span));
+
+        let error = parse_error("99999999 A");
+        assert_eq!(
+            ParseErrorKind::StackIdOutOfRange { max: MAX_STACK_ID },
+            error.kind
+        );
+
+        let error = parse_error("1 A BC D");
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::IllegalCrateLabel { .. }
+        ));
+        assert_eq!((4..6, "BC"), (error.span, error.text.as_str()));
+    }
+
+    #[test]
+    fn test_from_str_drawing_errors() {
+        let error = parse_error("[A] [BC]\n 1   2");
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::IllegalCrateLabel { .. }
+        ));
+        assert_eq!(
+            (1, 4..8, "[BC]"),
+            (error.line, error.span, error.text.as_str())
+        );
+
+        let error = parse_error("[A]  [B]\n 1   2");
+        assert_eq!(ParseErrorKind::MisalignedCrate, error.kind);
+
+        let error = parse_error("[A] [B]\n 1   1");
+        assert_eq!(
+            ParseErrorKind::DuplicateStackId { first_line: 2 },
+            error.kind
+        );
+
+        let error = parse_error("[A] [B]\n 1   0");
+        assert_eq!(ParseErrorKind::ZeroStackId, error.kind);
+    }
+
+    // Test that we can parse instructions correctly.
+    #[test]
+    fn test_instruction_parsing() {
+        let input = "move 1 from 2 to 1\nmove 3 from 1 to 3";
+        let instructions: CraneInstructions = input.parse().unwrap();
+        assert_eq!(2, instructions.instructions.len());
+        assert_eq!(1, instructions.instructions[0].num_to_move);
+        assert_eq!(0, instructions.instructions[0].to_stack);
+        assert_eq!(1, instructions.instructions[0].from_stack);
+        assert_eq!(3, instructions.instructions[1].num_to_move);
+        assert_eq!(2, instructions.instructions[1].to_stack);
+        assert_eq!(0, instructions.instructions[1].from_stack);
+    }
+
+    #[test]
+    fn test_instruction_parsing_rejects_missing_numbers() {
+        let result = CraneInstruction::parse_with("move 1 from 2", ParseMode::Lenient);
+        assert!(matches!(
+            result,
+            Err(ParseError {
+                kind: ParseErrorKind::InvalidInstruction,
+                ..
+            })
+        ));
+    }
+
+    fn instruction_error(input: &str) -> ParseError {
+        input.parse::<CraneInstruction>().unwrap_err()
+    }
+
+    #[test]
+    fn test_strict_instruction_errors() {
+        let error = instruction_error("teleport 1 bananas 2 3");
+        assert_eq!(
+            ParseErrorKind::UnexpectedToken { expected: "move" },
+            error.kind
+        );
+        assert_eq!((0..8, "teleport"), (error.span, error.text.as_str()));
+
+        let error = instruction_error("move 1 frm 2 to 3");
+        assert_eq!(
+            ParseErrorKind::UnexpectedToken { expected: "from" },
+            error.kind
+        );
+        assert_eq!(7..10, error.span);
+
+        let error = instruction_error("move x from 2 to 3");
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::CrateCountParseError(_)
+        ));
+
+        let error = instruction_error("move 1 from 0 to 3");
+        assert_eq!(
+            (ParseErrorKind::ZeroStackId, 12..13),
+            (error.kind, error.span)
+        );
+
+        let error = instruction_error("move 1 from 2 to -3");
+        assert_eq!(ParseErrorKind::NegativeStackId, error.kind);
+
+        let error = instruction_error("move 1 from 2");
+        assert_eq!(ParseErrorKind::MissingToken { expected: "to" }, error.kind);
+        assert_eq!(13..13, error.span);
+
+        let error = instruction_error("move 1 from 2 to 3 please");
+        assert_eq!((19..25, "please"), (error.span, error.text.as_str()));
+    }
+
+    #[test]
+    fn test_lenient_instruction_parsing() {
+        let instruction =
+            CraneInstruction::parse_with("teleport 1 bananas 2 3", ParseMode::Lenient).unwrap();
+        assert_eq!(
+            CraneInstruction {
+                num_to_move: 1,
+                from_stack: 1,
+                to_stack: 2,
+            },
+            instruction
+        );
+
+        let error =
+            CraneInstruction::parse_with("move 1 from 0 to 3", ParseMode::Lenient).unwrap_err();
+        assert_eq!(ParseErrorKind::ZeroStackId, error.kind);
+    }
+
+    #[test]
+    fn test_instruction_errors_report_line() {
+        let input = "move 1 from 2 to 1\n  bogus line\n";
+        let error = input.parse::<CraneInstructions>().unwrap_err();
+        assert_eq!(2, error.line);
+        assert_eq!(2..7, error.span);
+        assert_eq!("bogus", error.text);
+
+        let error = CraneInstructions::parse_with(input, ParseMode::Lenient).unwrap_err();
+        assert_eq!(2, error.line);
+        assert_eq!(2..12, error.span);
+        assert_eq!("bogus line", error.text);
+    }
+
+    #[test]
+    fn test_parse_all_reports_every_error() {
+        let input = "move 1 from 2 to 1\n\
+                     move 1 from 0 to 1\n\
+                     move 2 from 1 to 3\n\
+                     shuffle\n\
+                     move 3 from 3 to 2";
+        let report = CraneInstructions::parse_all(input, ParseMode::Strict);
+        assert!(!report.is_ok());
+        assert_eq!(3, report.instructions.len());
+        assert_eq!(vec![1, 3, 5], report.lines);
+        let error_lines: Vec<usize> = report.errors.iter().map(|e| e.line).collect();
+        assert_eq!(vec![2, 4], error_lines);
+        assert_eq!(ParseErrorKind::ZeroStackId, report.errors[0].kind);
+        assert_eq!(2, report.into_result().unwrap_err().len());
+
+        let report = CraneInstructions::parse_all("move 1 from 2 to 1", ParseMode::Strict);
+        assert_eq!(1, report.into_result().unwrap().len());
+    }
+
+    #[test]
+    fn test_puzzle_input() {
+        let input = "1 Z N\n2 M C D\n3 P\n\nmove 1 from 2 to 1\nmove 3 from 1 to 3\n";
+        let input: PuzzleInput = input.parse().unwrap();
+        assert_eq!(3, input.stacks.len());
+        assert_eq!(2, input.instructions.len());
+        assert_eq!(6, input.instruction_line(1));
+    }
+
+    #[test]
+    fn test_puzzle_input_errors_use_file_lines() {
+        let input = "1 Z N\n2 M C D\n\nmove 1 from 2 to 1\nmove 1 from 2 to 3\nmove 1 frm 1 to 2";
+        let errors = PuzzleInput::<char>::parse_with(input, ParseMode::Strict).unwrap_err();
+        assert_eq!(2, errors.len());
+        assert_eq!(
+            (5, ParseErrorKind::UnknownStack { num_stacks: 2 }, 17..18),
+            (
+                errors[0].line,
+                errors[0].kind.clone(),
+                errors[0].span.clone()
+            )
+        );
+        assert_eq!(6, errors[1].line);
+
+        let error = "1 Z N\n0 M\n\nmove 1 from 1 to 2"
+            .parse::<PuzzleInput>()
+            .unwrap_err();
+        assert_eq!((2, ParseErrorKind::ZeroStackId), (error.line, error.kind));
+
+        let error = "1 Z N\n2 M\n".parse::<PuzzleInput>().unwrap_err();
+        assert_eq!(ParseErrorKind::MissingBlankLine, error.kind);
+    }
+}
@@ -0,0 +1,339 @@
+//! Working out instructions that get the stacks into a desired state.
+//!
+//! Everything else in the library goes forward from stacks and
+//! instructions to the final stacks. A [`Planner`] goes the other way:
+//! given the starting stacks and a [`Goal`], it searches for a list of
+//! instructions that a particular crane can follow to meet the goal.
+//!
+//! The search is a uniform cost (Dijkstra) search over configurations,
+//! so the plan it finds is the cheapest by the chosen [`Objective`]. The
+//! number of configurations grows very quickly with the number of crates,
+//! so this is only practical for small inputs, and the search gives up
+//! after a configurable number of configurations.
+
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry, BinaryHeap, HashMap},
+};
+
+use crate::{
+    cost::CostModel,
+    crane::{Crane, CraneInstruction, CraneInstructions},
+    error::PlanError,
+    model::{Stack, Stacks},
+};
+
+/// The default number of configurations to explore before giving up.
+pub const DEFAULT_MAX_EXPLORED: usize = 100_000;
+
+/// What the stacks should look like at the end of a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Goal {
+    /// Exactly these stacks.
+    Stacks(Stacks),
+    /// Any stacks whose `tops_string` is this.
+    Tops(String),
+}
+
+impl Goal {
+    /// Whether these stacks meet the goal.
+    #[must_use]
+    pub fn is_met_by(&self, stacks: &Stacks) -> bool {
+        match self {
+            // Only the crates matter, not any capacity limits.
+            Self::Stacks(goal) => goal.stacks == stacks.stacks,
+            Self::Tops(tops) => stacks.tops_string().is_ok_and(|found| &found == tops),
+        }
+    }
+
+    /// Check that following `plan` with `crane` from `start` meets the goal,
+    /// returning the stacks it ends with.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PlanError::Replay` if one of the instructions can't be
+    /// applied, and `PlanError::GoalMissed` if they can but the result
+    /// doesn't meet the goal.
+    pub fn verify<C: Crane + ?Sized>(
+        &self,
+        start: &Stacks,
+        crane: &C,
+        plan: &CraneInstructions,
+    ) -> Result<Stacks, PlanError> {
+        let end = start
+            .clone()
+            .apply_instructions(crane, plan)
+            .map_err(PlanError::Replay)?;
+        if self.is_met_by(&end) {
+            Ok(end)
+        } else {
+            Err(PlanError::GoalMissed)
+        }
+    }
+
+    // Rule out goals that can't possibly be met, so we don't search every
+    // reachable configuration to find that out.
+    fn check_possible(&self, start: &Stacks) -> Result<(), PlanError> {
+        let mut available = crate_counts(start);
+        let found = match self {
+            Self::Stacks(goal) => {
+                if crate_counts(goal) != available {
+                    return Err(PlanError::DifferentCrates);
+                }
+                goal.len()
+            }
+            Self::Tops(tops) => {
+                for c in tops.chars() {
+                    match available.get_mut(&c) {
+                        Some(count) if *count > 0 => *count -= 1,
+                        _ => return Err(PlanError::DifferentCrates),
+                    }
+                }
+                tops.chars().count()
+            }
+        };
+        if found == start.len() {
+            Ok(())
+        } else {
+            Err(PlanError::WrongStackCount {
+                expected: start.len(),
+                found,
+            })
+        }
+    }
+}
+
+fn crate_counts(stacks: &Stacks) -> HashMap<char, usize> {
+    let mut counts = HashMap::new();
+    for c in stacks.iter().flat_map(Stack::iter) {
+        *counts.entry(*c).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// What makes one plan better than another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Objective {
+    /// Use as few instructions as possible.
+    #[default]
+    Instructions,
+    /// Lift as few crates as possible, in total.
+    CratesLifted,
+}
+
+/// Searches for instructions that meet a goal, created with
+/// [`Planner::new`] and configured with its builder methods.
+#[derive(Debug, Clone, Copy)]
+pub struct Planner<'a, C: ?Sized> {
+    crane: &'a C,
+    objective: Objective,
+    max_explored: usize,
+}
+
+/// A configuration the search has reached, and how it got there.
+struct Node {
+    stacks: Stacks,
+    cost: u64,
+    previous: Option<(usize, CraneInstruction)>,
+}
+
+impl<'a, C: Crane + ?Sized> Planner<'a, C> {
+    /// A planner for `crane` that minimises the number of instructions.
+    #[must_use]
+    pub const fn new(crane: &'a C) -> Self {
+        Self {
+            crane,
+            objective: Objective::Instructions,
+            max_explored: DEFAULT_MAX_EXPLORED,
+        }
+    }
+
+    /// Set what the plan should minimise.
+    #[must_use]
+    pub const fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Set how many configurations to explore before giving up.
+    #[must_use]
+    pub const fn max_explored(mut self, max_explored: usize) -> Self {
+        self.max_explored = max_explored;
+        self
+    }
+
+    /// Find the cheapest list of instructions that takes `start` to `goal`.
+    /// The plan is replayed with `Goal::verify` before it's returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `PlanError` if the goal can't be reached, or the search
+    /// gave up before reaching it.
+    pub fn plan(&self, start: &Stacks, goal: &Goal) -> Result<CraneInstructions, PlanError> {
+        goal.check_possible(start)?;
+
+        let mut nodes = vec![Node {
+            stacks: start.clone(),
+            cost: 0,
+            previous: None,
+        }];
+        let mut seen = HashMap::from([(start.clone(), 0)]);
+        let mut queue = BinaryHeap::from([Reverse((0, 0))]);
+        let mut explored = 0;
+
+        while let Some(Reverse((cost, id))) = queue.pop() {
+            // A configuration can be queued again when a cheaper way to it
+            // turns up, so skip the stale entries.
+            if cost > nodes[id].cost {
+                continue;
+            }
+            if goal.is_met_by(&nodes[id].stacks) {
+                let plan = Self::path_to(&nodes, id);
+                goal.verify(start, self.crane, &plan)?;
+                return Ok(plan);
+            }
+            if explored == self.max_explored {
+                return Err(PlanError::LimitReached { explored });
+            }
+            explored += 1;
+
+            let current = nodes[id].stacks.clone();
+            for instruction in moves(&current) {
+                let mut next = current.clone();
+                if next.apply_in_place(self.crane, &instruction).is_err() || next == current {
+                    continue;
+                }
+                let next_cost = cost + self.objective.cost(&instruction);
+                let next_id = match seen.entry(next) {
+                    Entry::Occupied(entry) => {
+                        let next_id = *entry.get();
+                        if nodes[next_id].cost <= next_cost {
+                            continue;
+                        }
+                        next_id
+                    }
+                    Entry::Vacant(entry) => {
+                        nodes.push(Node {
+                            stacks: entry.key().clone(),
+                            cost: next_cost,
+                            previous: None,
+                        });
+                        *entry.insert(nodes.len() - 1)
+                    }
+                };
+                nodes[next_id].cost = next_cost;
+                nodes[next_id].previous = Some((id, instruction));
+                queue.push(Reverse((next_cost, next_id)));
+            }
+        }
+        Err(PlanError::Unreachable)
+    }
+
+    fn path_to(nodes: &[Node], mut id: usize) -> CraneInstructions {
+        let mut instructions = Vec::new();
+        while let Some((previous, instruction)) = nodes[id].previous {
+            instructions.push(instruction);
+            id = previous;
+        }
+        instructions.reverse();
+        CraneInstructions { instructions }
+    }
+}
+
+/// Every instruction that could be applied to `stacks`, including moves
+/// onto the same stack, since some cranes rearrange crates that way.
+fn moves(stacks: &Stacks) -> impl Iterator<Item = CraneInstruction> + '_ {
+    stacks
+        .iter()
+        .enumerate()
+        .flat_map(move |(from_stack, from)| {
+            (0..stacks.len()).flat_map(move |to_stack| {
+                (1..=from.len()).map(move |num_to_move| CraneInstruction {
+                    num_to_move,
+                    from_stack,
+                    to_stack,
+                })
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::{CrateMover9000, CrateMover9001};
+
+    fn stacks(s: &str) -> Stacks {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_plan_to_stacks() {
+        let start = stacks("1 A B C\n2\n3");
+        let goal = Goal::Stacks(stacks("1\n2\n3 A B C"));
+        // The 9001 can move the whole stack in one go; the 9000 reverses
+        // crates as it moves them, so it needs at least two moves.
+        let plan = Planner::new(&CrateMover9001).plan(&start, &goal).unwrap();
+        assert_eq!(
+            "move 3 from 1 to 3",
+            plan.iter().next().unwrap().to_string()
+        );
+        assert_eq!(1, plan.len());
+        let plan = Planner::new(&CrateMover9000).plan(&start, &goal).unwrap();
+        assert_eq!(2, plan.len());
+        goal.verify(&start, &CrateMover9000, &plan).unwrap();
+    }
+
+    #[test]
+    fn test_plan_to_tops() {
+        let start = stacks("1 Z N\n2 M C D\n3 P");
+        let goal = Goal::Tops("CMZ".to_string());
+        let plan = Planner::new(&CrateMover9000).plan(&start, &goal).unwrap();
+        let end = goal.verify(&start, &CrateMover9000, &plan).unwrap();
+        assert_eq!("CMZ", end.tops_string().unwrap());
+    }
+
+    #[test]
+    fn test_objectives() {
+        // The 9000 can flip the stack over twice, or take a cheaper but
+        // longer route through the third stack.
+        let start = stacks("1 A B C\n2\n3");
+        let goal = Goal::Stacks(stacks("1\n2 A B C\n3"));
+        let lifted = |plan: &CraneInstructions| plan.iter().map(|i| i.num_to_move).sum::<usize>();
+
+        let by_count = Planner::new(&CrateMover9000).plan(&start, &goal).unwrap();
+        assert_eq!((2, 6), (by_count.len(), lifted(&by_count)));
+        let by_crates = Planner::new(&CrateMover9000)
+            .objective(Objective::CratesLifted)
+            .plan(&start, &goal)
+            .unwrap();
+        assert_eq!((3, 5), (by_crates.len(), lifted(&by_crates)));
+    }
+
+    #[test]
+    fn test_impossible_goals() {
+        let start = stacks("1 A\n2 B");
+        let planner = Planner::new(&CrateMover9001);
+        assert_eq!(
+            Err(PlanError::DifferentCrates),
+            planner.plan(&start, &Goal::Stacks(stacks("1 A\n2 C")))
+        );
+        assert_eq!(
+            Err(PlanError::WrongStackCount {
+                expected: 2,
+                found: 1
+            }),
+            planner.plan(&start, &Goal::Tops("A".to_string()))
+        );
+        // The 9001 can't reorder crates without somewhere to put them.
+        assert_eq!(
+            Err(PlanError::Unreachable),
+            planner.plan(&stacks("1 A B"), &Goal::Stacks(stacks("1 B A")))
+        );
+        assert_eq!(
+            Err(PlanError::LimitReached { explored: 1 }),
+            planner
+                .max_explored(1)
+                .plan(&stacks("1 A B C\n2 D"), &Goal::Tops("DC".to_string()))
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Crates that carry more than a label.
+//!
+//! A [`CrateRecord`] is a [`Label`] with optional tags for the crate's
+//! weight, whether it's fragile, and which stack it should end up on.
+//! In a stack configuration the tags follow the label, separated by
+//! colons:
+//!
+//! ```text
+//! 1 A:12kg:fragile B
+//! 2 C:3kg:dest=1
+//! ```
+
+use std::fmt::{self, Display};
+
+use crate::model::{Label, Stacks};
+
+/// A crate with its label and whatever tags it was given.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrateRecord<L = char> {
+    pub label: L,
+    /// The weight in kilograms, from a tag like `12kg`.
+    pub weight: Option<u32>,
+    /// From the tag `fragile`.
+    pub fragile: bool,
+    /// The (zero-based) index of the stack the crate should be delivered
+    /// to, from a tag like `dest=3`.
+    pub destination: Option<usize>,
+}
+
+impl<L> CrateRecord<L> {
+    /// A crate with just a label and no tags.
+    #[must_use]
+    pub const fn new(label: L) -> Self {
+        Self {
+            label,
+            weight: None,
+            fragile: false,
+            destination: None,
+        }
+    }
+
+    /// Record one tag, returning `None` if it isn't a tag we know or the
+    /// crate already has it.
+    fn tag(mut self, tag: &str) -> Option<Self> {
+        if let Some(weight) = tag.strip_suffix("kg") {
+            if self.weight.is_some() {
+                return None;
+            }
+            self.weight = Some(weight.parse().ok()?);
+        } else if let Some(stack) = tag.strip_prefix("dest=") {
+            if self.destination.is_some() {
+                return None;
+            }
+            let stack: usize = stack.parse().ok()?;
+            self.destination = Some(stack.checked_sub(1)?);
+        } else if tag == "fragile" && !self.fragile {
+            self.fragile = true;
+        } else {
+            return None;
+        }
+        Some(self)
+    }
+}
+
+// Each tag can be given at most once, in any order.
+impl<L: Label> Label for CrateRecord<L> {
+    const EXPECTED: &'static str =
+        "a label followed by optional `:12kg`, `:fragile`, and `:dest=3` tags";
+
+    fn from_token(token: &str) -> Option<Self> {
+        let mut parts = token.split(':');
+        let label = L::from_token(parts.next()?)?;
+        parts.try_fold(Self::new(label), Self::tag)
+    }
+
+    fn weight(&self) -> u64 {
+        self.weight.map_or(0, u64::from)
+    }
+}
+
+// The tags are written in a fixed order, so this parses back to the same
+// crate.
+impl<L: Display> Display for CrateRecord<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)?;
+        if let Some(weight) = self.weight {
+            write!(f, ":{weight}kg")?;
+        }
+        if self.fragile {
+            write!(f, ":fragile")?;
+        }
+        if let Some(stack) = self.destination {
+            write!(f, ":dest={}", stack + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl<L> Stacks<CrateRecord<L>> {
+    /// The total weight of each stack, counting crates without a weight
+    /// as weighing nothing.
+    #[must_use]
+    pub fn weights(&self) -> Vec<u64> {
+        self.iter()
+            .map(|stack| {
+                stack
+                    .iter()
+                    .filter_map(|record| record.weight)
+                    .map(u64::from)
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// The crates that have a destination but aren't on it, with the
+    /// index of the stack each one is on now.
+    pub fn undelivered(&self) -> impl Iterator<Item = (usize, &CrateRecord<L>)> {
+        self.iter().enumerate().flat_map(|(index, stack)| {
+            stack
+                .iter()
+                .filter(move |record| record.destination.is_some_and(|dest| dest != index))
+                .map(move |record| (index, record))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crane::CrateMover9001, error::ParseErrorKind, parse::PuzzleInput};
+
+    #[test]
+    fn test_tags() {
+        let record = CrateRecord::<char>::from_token("A:12kg:fragile:dest=3").unwrap();
+        assert_eq!(
+            CrateRecord {
+                label: 'A',
+                weight: Some(12),
+                fragile: true,
+                destination: Some(2),
+            },
+            record
+        );
+        assert_eq!("A:12kg:fragile:dest=3", record.to_string());
+        assert_eq!(
+            record,
+            CrateRecord::from_token("A:dest=3:fragile:12kg").unwrap()
+        );
+        assert_eq!(Some(CrateRecord::new('B')), CrateRecord::from_token("B"));
+        assert_eq!(
+            Some("SKU-1".to_string()),
+            CrateRecord::<String>::from_token("SKU-1:fragile").map(|record| record.label)
+        );
+
+        for bad in [
+            "AB:1kg",
+            "A:kg",
+            "A:1kg:2kg",
+            "A:heavy",
+            "A:dest=0",
+            "A:",
+            ":1kg",
+        ] {
+            assert_eq!(None, CrateRecord::<char>::from_token(bad), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_records_in_an_input() {
+        let input: PuzzleInput<CrateRecord> =
+            "1 A:12kg:dest=2 B:fragile\n2 C:3kg:dest=2\n\nmove 2 from 1 to 2\n"
+                .parse()
+                .unwrap();
+        assert_eq!(vec![12, 3], input.stacks.weights());
+        let labels: Vec<char> = input.stacks.undelivered().map(|(_, r)| r.label).collect();
+        assert_eq!(vec!['A'], labels);
+
+        let end = input.run(&CrateMover9001).unwrap();
+        assert_eq!(vec![0, 15], end.weights());
+        assert_eq!(0, end.undelivered().count());
+        assert_eq!(end, end.to_string().parse().unwrap());
+
+        let error = "1 A:12 B".parse::<Stacks<CrateRecord>>().unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::IllegalCrateLabel { .. }
+        ));
+        assert_eq!("A:12", error.text);
+    }
+}
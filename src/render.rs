@@ -0,0 +1,161 @@
+//! Rendering `Stacks` as the original Advent of Code column drawing.
+//!
+//! The `Display` implementation for `Stacks` prints the simplified one
+//! stack per line format. For showing the stacks to people, the familiar
+//! picture is usually nicer:
+//!
+//! ```text
+//!     [D]
+//! [N] [C]
+//! [Z] [M] [P]
+//!  1   2   3
+//! ```
+//!
+//! Any capacity limits are written as `capacity` lines above the picture,
+//! so the output of [`Drawing`] can be parsed back into the same `Stacks`.
+
+use std::fmt::{self, Display};
+
+use crate::model::{Stack, Stacks};
+
+/// The standard Advent of Code layout: `[X]` plus one space between stacks.
+pub const DEFAULT_COLUMN_WIDTH: usize = 4;
+
+/// A column drawing of a set of stacks, created by [`Stacks::drawing`].
+#[derive(Debug, Clone, Copy)]
+pub struct Drawing<'a> {
+    stacks: &'a Stacks,
+    column_width: usize,
+    pad_rows: bool,
+}
+
+impl Stacks {
+    /// Render these stacks as a column drawing, with the default layout.
+    #[must_use]
+    pub const fn drawing(&self) -> Drawing<'_> {
+        Drawing {
+            stacks: self,
+            column_width: DEFAULT_COLUMN_WIDTH,
+            pad_rows: false,
+        }
+    }
+}
+
+impl Drawing<'_> {
+    /// Set the number of characters each stack takes up, including the
+    /// gap to the next stack. Widths too narrow to fit a crate or the
+    /// stack numbers in the footer are widened as needed.
+    #[must_use]
+    pub const fn column_width(mut self, column_width: usize) -> Self {
+        self.column_width = column_width;
+        self
+    }
+
+    /// Pad every row with trailing spaces to the full width of the drawing,
+    /// as in the original puzzle input. By default trailing spaces above
+    /// shorter stacks are left off.
+    #[must_use]
+    pub const fn pad_rows(mut self, pad_rows: bool) -> Self {
+        self.pad_rows = pad_rows;
+        self
+    }
+
+    // The footer numbers are right-aligned under the crate labels, so wide
+    // stack numbers need wide enough columns to keep a space between them.
+    fn effective_width(&self) -> usize {
+        let digits = self.stacks.len().to_string().len();
+        self.column_width.max(DEFAULT_COLUMN_WIDTH).max(digits + 1)
+    }
+}
+
+impl Display for Drawing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.effective_width();
+        let height = self.stacks.iter().map(Stack::len).max().unwrap_or(0);
+        write!(f, "{}", self.stacks.capacity())?;
+
+        // Rows are written from the top of the tallest stack down to the bottom.
+        for level in (0..height).rev() {
+            let mut row = String::new();
+            for (i, stack) in self.stacks.iter().enumerate() {
+                if i > 0 {
+                    row.push_str(&" ".repeat(width - 3));
+                }
+                match stack.stack.get(level) {
+                    Some(&c) => row.extend(['[', c, ']']),
+                    None => row.push_str("   "),
+                }
+            }
+            if !self.pad_rows {
+                row.truncate(row.trim_end().len());
+            }
+            writeln!(f, "{row}")?;
+        }
+
+        // Each stack number ends under the label of its crates, one
+        // character into the column.
+        let mut footer = String::new();
+        for i in 0..self.stacks.len() {
+            let id = (i + 1).to_string();
+            let end = i * width + 2;
+            footer.push_str(&" ".repeat(end - id.len() - footer.len()));
+            footer.push_str(&id);
+        }
+        writeln!(f, "{footer}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_drawing() {
+        let stacks: Stacks = "1 Z N\n2 M C D\n3 P".parse().unwrap();
+        let expected = "    [D]\n\
+                        [N] [C]\n\
+                        [Z] [M] [P]\n \
+                         1   2   3\n";
+        assert_eq!(expected, stacks.drawing().to_string());
+    }
+
+    #[test]
+    fn test_drawing_padded_and_wide() {
+        let stacks: Stacks = "1 Z N\n2 M".parse().unwrap();
+        let expected = "[N]      \n\
+                        [Z]   [M]\n \
+                         1     2\n";
+        let drawing = stacks.drawing().column_width(6).pad_rows(true);
+        assert_eq!(expected, drawing.to_string());
+        assert_eq!(stacks, drawing.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn test_drawing_round_trips_original_input() {
+        let contents = fs::read_to_string("original_input.txt").unwrap();
+        let (drawing, _) = contents.split_once("\n\n").unwrap();
+        let stacks: Stacks = drawing.parse().unwrap();
+        assert_eq!(format!("{drawing}\n"), stacks.drawing().to_string());
+    }
+
+    #[test]
+    fn test_drawing_keeps_capacity() {
+        let stacks: Stacks = "capacity 5\ncapacity 3 for 2\n1 A B\n2 C".parse().unwrap();
+        let expected = "capacity 5\n\
+                        capacity 3 for 2\n\
+                        [B]\n\
+                        [A] [C]\n \
+                         1   2\n";
+        assert_eq!(expected, stacks.drawing().to_string());
+        assert_eq!(stacks, stacks.drawing().to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn test_drawing_round_trips_many_stacks() {
+        let stacks: Stacks = "1 A\n7 B C\n12 D\n105 E F G".parse().unwrap();
+        let reparsed: Stacks = stacks.drawing().to_string().parse().unwrap();
+        assert_eq!(stacks, reparsed);
+    }
+}
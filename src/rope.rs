@@ -0,0 +1,453 @@
+//! A stack storage where moving crates doesn't depend on how many move.
+//!
+//! `Stacks` keeps each stack in a `Vec`, so every move copies the moved
+//! crates, which is fine for the puzzle but slow for generated inputs with
+//! millions of moves of thousands of crates. [`RopeStacks`] keeps each
+//! stack as an implicit treap (a balanced tree ordered by position, bottom
+//! to top) with a lazy "reversed" flag on each subtree. Moving the top `n`
+//! crates is then a split of the source tree and a merge onto the
+//! destination, and the 9000's reversal is flipping one flag, so a move
+//! takes `O(log n)` expected time however many crates it moves.
+//!
+//! `RopeStacks` has the same methods as `Stacks` for applying instructions
+//! and reading the result, and converts to and from it. [`StackBackend`]
+//! chooses between the two at runtime.
+
+use crate::{
+    capacity::Capacity,
+    crane::{Crane, CraneInstruction, CraneInstructions, Landing},
+    error::{CraneError, InstructionFailure},
+    lift::Lifts,
+    model::{Stack, Stacks},
+    parse::PuzzleInput,
+};
+
+/// One crate, and the root of the subtree of crates under it.
+#[derive(Debug, Clone)]
+struct Node {
+    label: char,
+    /// Treap priority: parents always have higher priorities than their
+    /// children, which keeps the tree balanced with high probability.
+    priority: u32,
+    /// The number of crates in this subtree.
+    size: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+    /// This whole subtree still needs to be reversed.
+    reversed: bool,
+}
+
+/// A set of stacks stored as trees, for inputs with very large moves.
+#[derive(Debug, Clone)]
+pub struct RopeStacks {
+    /// Every crate, referred to by index. Moves only relink nodes, so
+    /// this only grows when a crane without a `Landing` is used.
+    nodes: Vec<Node>,
+    /// The root of each stack's tree, or `None` if it's empty.
+    roots: Vec<Option<usize>>,
+    /// State for generating priorities.
+    seed: u32,
+    capacity: Capacity,
+}
+
+impl RopeStacks {
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// The number of crates on the stack at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no stack at `index`.
+    #[must_use]
+    pub fn height(&self, index: usize) -> usize {
+        self.size(self.roots[index])
+    }
+
+    /// Return the top crate of each stack as a string, like `Stacks::tops_string`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CraneError::EmptyStack` if any of the stacks are empty.
+    pub fn tops_string(&self) -> Result<String, CraneError> {
+        (0..self.len())
+            .map(|stack| self.top(stack).ok_or(CraneError::EmptyStack { stack }))
+            .collect()
+    }
+
+    /// The top crate of the stack at `index`, if it has any.
+    fn top(&self, index: usize) -> Option<char> {
+        let mut node = &self.nodes[self.roots[index]?];
+        let mut reversed = false;
+        loop {
+            // A reversed subtree has its children swapped, so the top is
+            // down the left side instead of the right.
+            reversed ^= node.reversed;
+            let next = if reversed { node.left } else { node.right };
+            match next {
+                Some(next) => node = &self.nodes[next],
+                None => return Some(node.label),
+            }
+        }
+    }
+
+    /// Apply a single instruction, like `Stacks::apply_instruction`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Stacks::apply_instruction`.
+    pub fn apply_instruction<C: Crane + ?Sized>(
+        mut self,
+        crane: &C,
+        instruction: &CraneInstruction,
+    ) -> Result<Self, CraneError> {
+        self.apply_in_place(crane, instruction)?;
+        Ok(self)
+    }
+
+    /// Apply a single instruction in place, like `Stacks::apply_in_place`.
+    /// If the instruction is invalid the stacks are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Stacks::apply_instruction`.
+    pub fn apply_in_place<C: Crane + ?Sized>(
+        &mut self,
+        crane: &C,
+        instruction: &CraneInstruction,
+    ) -> Result<(), CraneError> {
+        // Crates in a rope don't have weights.
+        let lifts = Lifts::plan(
+            instruction,
+            self.len(),
+            &self.capacity,
+            |stack| self.height(stack),
+            crane.landing(),
+            crane.lift_limit(),
+            |_| 0,
+        )?;
+        for lift in lifts.as_slice() {
+            self.lift(crane, lift);
+        }
+        Ok(())
+    }
+
+    /// Make a single lift that's already been checked.
+    fn lift<C: Crane + ?Sized>(&mut self, crane: &C, instruction: &CraneInstruction) {
+        let CraneInstruction {
+            num_to_move,
+            from_stack,
+            to_stack,
+        } = *instruction;
+        let (rest, group) = self.split(
+            self.roots[from_stack],
+            self.height(from_stack) - num_to_move,
+        );
+        self.roots[from_stack] = rest;
+
+        if let Some(landing) = crane.landing() {
+            if let (Landing::Reversed, Some(group)) = (landing, group) {
+                self.nodes[group].reversed ^= true;
+            }
+            self.roots[to_stack] = self.merge(self.roots[to_stack], group);
+        } else {
+            // We don't know what the crane does with the group, so let it
+            // move them between two `Stack`s and put back whatever ends up
+            // on each.
+            let mut from = Stack::from(self.labels(group));
+            let mut landed = Stack::default();
+            crane.transfer(&mut from, &mut landed, num_to_move);
+            for (stack, crates) in [(from_stack, from), (to_stack, landed)] {
+                for label in crates.iter() {
+                    let node = self.new_node(*label);
+                    self.roots[stack] = self.merge(self.roots[stack], Some(node));
+                }
+            }
+        }
+    }
+
+    /// Perform each of these instructions in order, like
+    /// `Stacks::apply_instructions`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Stacks::apply_instructions`.
+    pub fn apply_instructions<C: Crane + ?Sized>(
+        mut self,
+        crane: &C,
+        instructions: &CraneInstructions,
+    ) -> Result<Self, CraneError> {
+        for (index, instruction) in instructions.iter().enumerate() {
+            if let Err(cause) = self.apply_in_place(crane, instruction) {
+                return Err(CraneError::Instruction(Box::new(InstructionFailure {
+                    index,
+                    line: None,
+                    instruction: *instruction,
+                    before: self.to_stacks(),
+                    cause,
+                })));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Copy the crates out into ordinary `Stacks`.
+    #[must_use]
+    pub fn to_stacks(&self) -> Stacks {
+        let mut stacks: Stacks = self
+            .roots
+            .iter()
+            .map(|&root| Stack::from(self.labels(root)))
+            .collect();
+        stacks.set_capacity(self.capacity.clone());
+        stacks
+    }
+
+    /// The labels in a subtree, bottom to top.
+    fn labels(&self, root: Option<usize>) -> Vec<char> {
+        let mut labels = Vec::with_capacity(self.size(root));
+        // An in-order walk, with an explicit stack of the nodes whose
+        // lower halves we're still in, and whether each is reversed.
+        let mut pending = Vec::new();
+        let (mut current, mut reversed) = (root, false);
+        loop {
+            while let Some(node) = current {
+                let node_reversed = reversed ^ self.nodes[node].reversed;
+                pending.push((node, node_reversed));
+                let Node { left, right, .. } = self.nodes[node];
+                current = if node_reversed { right } else { left };
+                reversed = node_reversed;
+            }
+            let Some((node, node_reversed)) = pending.pop() else {
+                return labels;
+            };
+            let Node {
+                label, left, right, ..
+            } = self.nodes[node];
+            labels.push(label);
+            current = if node_reversed { left } else { right };
+            reversed = node_reversed;
+        }
+    }
+
+    fn size(&self, root: Option<usize>) -> usize {
+        root.map_or(0, |node| self.nodes[node].size)
+    }
+
+    fn new_node(&mut self, label: char) -> usize {
+        // xorshift32, which is plenty random enough for priorities.
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.nodes.push(Node {
+            label,
+            priority: self.seed,
+            size: 1,
+            left: None,
+            right: None,
+            reversed: false,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Apply a pending reversal to the node's children.
+    fn push_down(&mut self, node: usize) {
+        let Node {
+            reversed,
+            left,
+            right,
+            ..
+        } = self.nodes[node];
+        if reversed {
+            for child in [left, right].into_iter().flatten() {
+                self.nodes[child].reversed ^= true;
+            }
+            let node = &mut self.nodes[node];
+            (node.left, node.right) = (right, left);
+            node.reversed = false;
+        }
+    }
+
+    fn update_size(&mut self, node: usize) {
+        let Node { left, right, .. } = self.nodes[node];
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+    }
+
+    /// Split a tree into its bottom `k` crates and the rest.
+    fn split(&mut self, root: Option<usize>, k: usize) -> (Option<usize>, Option<usize>) {
+        let Some(node) = root else {
+            return (None, None);
+        };
+        self.push_down(node);
+        let left_size = self.size(self.nodes[node].left);
+        if k <= left_size {
+            let (bottom, top) = self.split(self.nodes[node].left, k);
+            self.nodes[node].left = top;
+            self.update_size(node);
+            (bottom, Some(node))
+        } else {
+            let (bottom, top) = self.split(self.nodes[node].right, k - left_size - 1);
+            self.nodes[node].right = bottom;
+            self.update_size(node);
+            (Some(node), top)
+        }
+    }
+
+    /// Join two trees, with all of `top` above all of `bottom`.
+    fn merge(&mut self, bottom: Option<usize>, top: Option<usize>) -> Option<usize> {
+        let (Some(b), Some(t)) = (bottom, top) else {
+            return bottom.or(top);
+        };
+        if self.nodes[b].priority > self.nodes[t].priority {
+            self.push_down(b);
+            let right = self.merge(self.nodes[b].right, top);
+            self.nodes[b].right = right;
+            self.update_size(b);
+            Some(b)
+        } else {
+            self.push_down(t);
+            let left = self.merge(bottom, self.nodes[t].left);
+            self.nodes[t].left = left;
+            self.update_size(t);
+            Some(t)
+        }
+    }
+}
+
+impl From<&Stacks> for RopeStacks {
+    fn from(stacks: &Stacks) -> Self {
+        let mut rope = Self {
+            nodes: Vec::new(),
+            roots: vec![None; stacks.len()],
+            seed: 0x2022_0005,
+            capacity: stacks.capacity().clone(),
+        };
+        for (index, stack) in stacks.iter().enumerate() {
+            for label in stack.iter() {
+                let node = rope.new_node(*label);
+                rope.roots[index] = rope.merge(rope.roots[index], Some(node));
+            }
+        }
+        rope
+    }
+}
+
+impl From<&RopeStacks> for Stacks {
+    fn from(rope: &RopeStacks) -> Self {
+        rope.to_stacks()
+    }
+}
+
+/// Which storage to apply instructions with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StackBackend {
+    /// `Stacks`, where each move copies the moved crates.
+    #[default]
+    Vec,
+    /// `RopeStacks`, where each move takes about the same time however
+    /// many crates it moves.
+    Rope,
+}
+
+impl PuzzleInput {
+    /// Apply all the instructions like `run`, using the given storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `run`.
+    pub fn run_with<C: Crane + ?Sized>(
+        &self,
+        crane: &C,
+        backend: StackBackend,
+    ) -> Result<Stacks, CraneError> {
+        match backend {
+            StackBackend::Vec => self.run(crane),
+            StackBackend::Rope => RopeStacks::from(&self.stacks)
+                .apply_instructions(crane, &self.instructions)
+                .map(|rope| rope.to_stacks())
+                .map_err(|error| self.locate_error(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        crane::{CraneModel, CrateMover9000, CrateMover9001},
+        test_util::{random_moves, Lcg},
+    };
+
+    #[test]
+    fn test_matches_vec_backend() {
+        let stacks: Stacks = "1 A B C D E\n2 F G\n3\n4 H I J K L M N".parse().unwrap();
+        let instructions = random_moves(&mut Lcg::new(5), &stacks, 2000);
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let expected = stacks.clone().apply_instructions(&crane, &instructions);
+            let rope = RopeStacks::from(&stacks).apply_instructions(&crane, &instructions);
+            assert_eq!(expected.unwrap(), rope.unwrap().to_stacks());
+        }
+    }
+
+    #[test]
+    fn test_run_with_rope_backend() {
+        let contents = fs::read_to_string("input.txt").unwrap();
+        let input: PuzzleInput = contents.parse().unwrap();
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            assert_eq!(
+                input.run(&crane),
+                input.run_with(&crane, StackBackend::Rope)
+            );
+        }
+        let rope = RopeStacks::from(&input.stacks)
+            .apply_instructions(&CrateMover9001, &input.instructions)
+            .unwrap();
+        assert_eq!("RGLVRCQSB", rope.tops_string().unwrap());
+    }
+
+    #[test]
+    fn test_errors_match_vec_backend() {
+        let input: PuzzleInput = "1 A B\n2 C\n\nmove 1 from 1 to 2\nmove 3 from 1 to 2\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            input.run(&CrateMover9000),
+            input.run_with(&CrateMover9000, StackBackend::Rope)
+        );
+        let stacks: Stacks = "1 A\n2".parse().unwrap();
+        assert_eq!(
+            Err(CraneError::EmptyStack { stack: 1 }),
+            RopeStacks::from(&stacks).tops_string()
+        );
+    }
+
+    // Cranes without a `Landing` still work, via `transfer`.
+    struct OneAtATime;
+
+    impl Crane for OneAtATime {
+        fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, n: usize) {
+            CrateMover9000.transfer(from, to, n);
+        }
+    }
+
+    #[test]
+    fn test_crane_without_landing() {
+        let stacks: Stacks = "1 A B C\n2 D".parse().unwrap();
+        let instructions = random_moves(&mut Lcg::new(5), &stacks, 200);
+        let expected = stacks
+            .clone()
+            .apply_instructions(&CrateMover9000, &instructions)
+            .unwrap();
+        let rope = RopeStacks::from(&stacks).apply_instructions(&OneAtATime, &instructions);
+        assert_eq!(expected, rope.unwrap().to_stacks());
+    }
+}
@@ -0,0 +1,214 @@
+//! Applying instructions as they're read, for inputs too big for memory.
+//!
+//! `PuzzleInput` holds the whole input and every instruction at once. An
+//! [`InputStream`] instead reads the stack configuration from any
+//! `BufRead` and then applies each instruction as soon as its line has
+//! been read, so only the stacks and the current line are kept. Errors
+//! are numbered with lines of the whole input, as with `PuzzleInput`.
+
+use std::io::BufRead;
+
+use crate::{
+    capacity::OverflowPolicy,
+    crane::{Crane, CraneInstruction},
+    error::{CraneError, InstructionFailure, ParseError, ParseErrorKind, StreamError},
+    model::Stacks,
+    parse::{parse_instruction_line, ParseMode},
+};
+
+/// A puzzle input being read from `R`, with the stacks as they are
+/// after the instructions read so far.
+#[derive(Debug)]
+pub struct InputStream<R> {
+    reader: R,
+    mode: ParseMode,
+    stacks: Stacks,
+    /// The number of lines read so far.
+    line: usize,
+    /// The number of instructions applied so far.
+    applied: usize,
+    /// The current line, reused so reading doesn't allocate every time.
+    buffer: String,
+}
+
+impl<R: BufRead> InputStream<R> {
+    /// Read the stack configuration and the blank line after it, leaving
+    /// the instructions to be read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreamError::Io` if reading fails, and `StreamError::Parse`
+    /// if the configuration isn't valid or there's no blank line after it.
+    pub fn new(reader: R, mode: ParseMode) -> Result<Self, StreamError> {
+        let mut stream = Self {
+            reader,
+            mode,
+            stacks: Stacks::default(),
+            line: 0,
+            applied: 0,
+            buffer: String::new(),
+        };
+        let mut config = String::new();
+        loop {
+            if !stream.read_line()? {
+                return Err(ParseError::new(
+                    ParseErrorKind::MissingBlankLine,
+                    stream.line + 1,
+                    0..0,
+                    "",
+                )
+                .into());
+            }
+            if stream.buffer.is_empty() {
+                break;
+            }
+            if !config.is_empty() {
+                config.push('\n');
+            }
+            config.push_str(&stream.buffer);
+        }
+        stream.stacks = config.parse()?;
+        Ok(stream)
+    }
+
+    /// The stacks after the instructions applied so far.
+    #[must_use]
+    pub const fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+
+    /// The number of instructions applied so far.
+    #[must_use]
+    pub const fn applied(&self) -> usize {
+        self.applied
+    }
+
+    /// Change what happens when an instruction would take a stack past
+    /// the capacity declared in the configuration.
+    pub const fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.stacks.set_overflow_policy(policy);
+    }
+
+    /// Read the next instruction and apply it with `crane`. Returns the
+    /// instruction, or `None` at the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreamError::Io` if reading fails, `StreamError::Parse` if
+    /// the line isn't a valid instruction, and `StreamError::Crane` if it
+    /// can't be applied, with the stacks left as they were.
+    pub fn apply_next<C: Crane + ?Sized>(
+        &mut self,
+        crane: &C,
+    ) -> Result<Option<CraneInstruction>, StreamError> {
+        if !self.read_line()? {
+            return Ok(None);
+        }
+        let instruction =
+            parse_instruction_line(&self.buffer, self.mode, self.line, Some(self.stacks.len()))?;
+        if let Err(cause) = self.stacks.apply_in_place(crane, &instruction) {
+            return Err(CraneError::Instruction(Box::new(InstructionFailure {
+                index: self.applied,
+                line: Some(self.line),
+                instruction,
+                before: self.stacks.clone(),
+                cause,
+            }))
+            .into());
+        }
+        self.applied += 1;
+        Ok(Some(instruction))
+    }
+
+    /// Apply all the remaining instructions and return the final stacks.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `apply_next`.
+    pub fn run<C: Crane + ?Sized>(mut self, crane: &C) -> Result<Stacks, StreamError> {
+        while self.apply_next(crane)?.is_some() {}
+        Ok(self.stacks)
+    }
+
+    /// Read the next line into the buffer without its line ending, the
+    /// way `str::lines` splits lines. Returns `false` at the end of the input.
+    fn read_line(&mut self) -> Result<bool, StreamError> {
+        self.buffer.clear();
+        if self.reader.read_line(&mut self.buffer)? == 0 {
+            return Ok(false);
+        }
+        if self.buffer.ends_with('\n') {
+            self.buffer.pop();
+            if self.buffer.ends_with('\r') {
+                self.buffer.pop();
+            }
+        }
+        self.line += 1;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::BufReader};
+
+    use super::*;
+    use crate::{
+        crane::{CrateMover9000, CrateMover9001},
+        parse::PuzzleInput,
+    };
+
+    #[test]
+    fn test_matches_puzzle_input() {
+        let contents = fs::read_to_string("input.txt").unwrap();
+        let input: PuzzleInput = contents.parse().unwrap();
+        let file = fs::File::open("input.txt").unwrap();
+        let stream = InputStream::new(BufReader::new(file), ParseMode::Strict).unwrap();
+        assert_eq!(input.stacks, *stream.stacks());
+        assert_eq!(
+            input.run(&CrateMover9001).unwrap(),
+            stream.run(&CrateMover9001).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_drawing_and_crlf() {
+        let source =
+            "    [D]\r\n[N] [C]\r\n[Z] [M] [P]\r\n 1   2   3\r\n\r\nmove 1 from 2 to 1\r\n";
+        let mut stream = InputStream::new(source.as_bytes(), ParseMode::Strict).unwrap();
+        assert_eq!(3, stream.stacks().len());
+        assert!(stream.apply_next(&CrateMover9000).unwrap().is_some());
+        assert_eq!(None, stream.apply_next(&CrateMover9000).unwrap());
+        assert_eq!(1, stream.applied());
+        assert_eq!("DCP", stream.stacks().tops_string().unwrap());
+    }
+
+    #[test]
+    fn test_errors_have_input_lines() {
+        let source = "1 A\n2 B\n\nmove 1 from 1 to 2\nmove 1 from 3 to 2\n";
+        let Err(StreamError::Parse(error)) = InputStream::new(source.as_bytes(), ParseMode::Strict)
+            .and_then(|s| s.run(&CrateMover9000))
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(5, error.line);
+        assert!(matches!(error.kind, ParseErrorKind::UnknownStack { .. }));
+
+        let source = "1 A\n2 B\n\nmove 1 from 1 to 2\nmove 1 from 1 to 2\n";
+        let input: PuzzleInput = source.parse().unwrap();
+        let Err(StreamError::Crane(error)) = InputStream::new(source.as_bytes(), ParseMode::Strict)
+            .and_then(|s| s.run(&CrateMover9000))
+        else {
+            panic!("expected a crane error");
+        };
+        assert_eq!(input.run(&CrateMover9000).unwrap_err(), error);
+
+        let Err(StreamError::Parse(error)) =
+            InputStream::new(&b"1 A\n2 B\n"[..], ParseMode::Strict)
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(ParseErrorKind::MissingBlankLine, error.kind);
+        assert_eq!(3, error.line);
+    }
+}
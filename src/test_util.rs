@@ -0,0 +1,57 @@
+//! Helpers shared by the tests and the benchmarks, built for the
+//! benchmarks with the `test-util` feature.
+
+use crate::{Crane, CraneInstruction, CraneInstructions, Stack, Stacks};
+
+/// A crane that only ever moves the single top crate, and so has no
+/// `Landing`, for checking that things work with cranes defined elsewhere.
+pub struct TopOnly;
+
+impl Crane for TopOnly {
+    fn transfer<L>(&self, from: &mut Stack<L>, to: &mut Stack<L>, _n: usize) {
+        to.extend(from.take_top(1));
+    }
+}
+
+/// A linear congruential generator, so random inputs are the same on
+/// every run.
+pub struct Lcg(u64);
+
+impl Lcg {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A number below `bound`.
+    ///
+    /// # Panics
+    ///
+    /// If `bound` is 0.
+    pub fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        usize::try_from(self.0 >> 33).unwrap() % bound
+    }
+}
+
+/// A list of valid random moves on `stacks`, including same-stack ones.
+pub fn random_moves(rng: &mut Lcg, stacks: &Stacks, count: usize) -> CraneInstructions {
+    let mut heights: Vec<usize> = stacks.iter().map(Stack::len).collect();
+    (0..count)
+        .map(|_| {
+            let from_stack = rng.next(heights.len());
+            let to_stack = rng.next(heights.len());
+            let num_to_move = rng.next(heights[from_stack] + 1);
+            heights[from_stack] -= num_to_move;
+            heights[to_stack] += num_to_move;
+            CraneInstruction {
+                num_to_move,
+                from_stack,
+                to_stack,
+            }
+        })
+        .collect()
+}
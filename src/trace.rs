@@ -0,0 +1,245 @@
+//! Running instructions one step at a time.
+//!
+//! `Stacks::apply_instructions` only hands back the final state, which
+//! isn't much help when the answer comes out wrong. A [`Trace`] applies
+//! the same instructions lazily, yielding a [`Step`] for each one that
+//! says which crates moved and how tall every stack is afterwards. The
+//! full intermediate state is available from [`Trace::stacks`] between
+//! steps.
+
+use std::{
+    fmt::{self, Display},
+    iter::Enumerate,
+    slice,
+};
+
+use crate::{
+    crane::{Crane, CraneInstruction, CraneInstructions},
+    error::{CraneError, InstructionFailure},
+    model::{Stack, Stacks},
+    parse::PuzzleInput,
+};
+
+/// What a single instruction did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The position of the instruction in the list, starting at 0.
+    pub index: usize,
+    /// The instruction as it was carried out, which moves fewer crates
+    /// than the one in the list if a stack capacity clamped it.
+    pub instruction: CraneInstruction,
+    /// The crates that moved, bottom to top as they landed on the
+    /// destination stack. This is fewer crates than the instruction says
+    /// if the crane didn't land them all.
+    pub moved: Vec<char>,
+    /// The height of every stack after the instruction.
+    pub heights: Vec<usize>,
+}
+
+// A compact one line summary, e.g. `step 2: move 3 from 1 to 3; moved D N Z; heights 0 2 4`.
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {}: {}; moved", self.index + 1, self.instruction)?;
+        for c in &self.moved {
+            write!(f, " {c}")?;
+        }
+        write!(f, "; heights")?;
+        for height in &self.heights {
+            write!(f, " {height}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator that applies a list of instructions one at a time,
+/// created by [`Stacks::trace`] or [`PuzzleInput::trace`].
+///
+/// Each item is the `Step` for the next instruction, or the error from
+/// the first instruction that can't be applied, after which the
+/// iterator stops.
+#[derive(Debug, Clone)]
+pub struct Trace<'a, C: ?Sized> {
+    stacks: Stacks,
+    crane: &'a C,
+    instructions: Enumerate<slice::Iter<'a, CraneInstruction>>,
+    /// The input line of the first instruction, if we know it.
+    first_line: Option<usize>,
+    failed: bool,
+}
+
+impl<C: ?Sized> Trace<'_, C> {
+    /// The stacks after the steps yielded so far. After an error these
+    /// are the stacks just before the failed instruction.
+    #[must_use]
+    pub const fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+
+    /// Stop tracing and take the stacks as they are now.
+    #[must_use]
+    pub fn into_stacks(self) -> Stacks {
+        self.stacks
+    }
+}
+
+impl<C: Crane + ?Sized> Iterator for Trace<'_, C> {
+    type Item = Result<Step, CraneError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let (index, instruction) = self.instructions.next()?;
+        // A stack capacity might mean fewer crates are moved.
+        let (admitted, base) = match self.stacks.plan_lifts(self.crane, instruction) {
+            Ok(lifts) => {
+                let admitted = lifts.admitted;
+                // The crane might land fewer crates than it was asked to, so
+                // the moved crates are the ones above the old height. A move
+                // onto the same stack puts its crates back where they were.
+                let mut base = self.stacks.stacks[admitted.to_stack].len();
+                if admitted.from_stack == admitted.to_stack {
+                    base -= admitted.num_to_move;
+                }
+                for lift in lifts.as_slice() {
+                    self.stacks.lift(self.crane, lift);
+                }
+                (admitted, base)
+            }
+            Err(cause) => {
+                self.failed = true;
+                return Some(Err(CraneError::Instruction(Box::new(InstructionFailure {
+                    index,
+                    line: self.first_line.map(|line| line + index),
+                    instruction: *instruction,
+                    before: self.stacks.clone(),
+                    cause,
+                }))));
+            }
+        };
+        let to = &self.stacks.stacks[admitted.to_stack].stack;
+        Some(Ok(Step {
+            index,
+            instruction: admitted,
+            moved: to[base..].to_vec(),
+            heights: self.stacks.iter().map(Stack::len).collect(),
+        }))
+    }
+}
+
+impl Stacks {
+    /// Apply `instructions` to these stacks one at a time, yielding what
+    /// each one did.
+    #[must_use]
+    pub fn trace<'a, C: Crane + ?Sized>(
+        self,
+        crane: &'a C,
+        instructions: &'a CraneInstructions,
+    ) -> Trace<'a, C> {
+        Trace {
+            stacks: self,
+            crane,
+            instructions: instructions.instructions.iter().enumerate(),
+            first_line: None,
+            failed: false,
+        }
+    }
+}
+
+impl PuzzleInput {
+    /// Trace the instructions on a copy of the stacks. Errors have the
+    /// input line of the failed instruction filled in, as with `run`.
+    #[must_use]
+    pub fn trace<'a, C: Crane + ?Sized>(&'a self, crane: &'a C) -> Trace<'a, C> {
+        Trace {
+            first_line: Some(self.first_instruction_line),
+            ..self.stacks.clone().trace(crane, &self.instructions)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        capacity::OverflowPolicy,
+        crane::{CrateMover9000, CrateMover9001},
+        test_util::TopOnly,
+    };
+
+    const EXAMPLE: &str = "1 Z N\n2 M C D\n3 P\n\n\
+                           move 1 from 2 to 1\n\
+                           move 3 from 1 to 3\n\
+                           move 2 from 2 to 1\n\
+                           move 1 from 1 to 2\n";
+
+    #[test]
+    fn test_trace_steps() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let steps: Vec<Step> = input.trace(&CrateMover9000).map(Result::unwrap).collect();
+        assert_eq!(4, steps.len());
+        assert_eq!(vec!['D', 'N', 'Z'], steps[1].moved);
+        assert_eq!(vec![0, 2, 4], steps[1].heights);
+        assert_eq!(
+            "step 2: move 3 from 1 to 3; moved D N Z; heights 0 2 4",
+            steps[1].to_string()
+        );
+
+        let steps: Vec<Step> = input.trace(&CrateMover9001).map(Result::unwrap).collect();
+        assert_eq!(vec!['Z', 'N', 'D'], steps[1].moved);
+    }
+
+    #[test]
+    fn test_trace_ends_where_run_does() {
+        let input: PuzzleInput = EXAMPLE.parse().unwrap();
+        let mut trace = input.trace(&CrateMover9000);
+        assert!(trace.by_ref().all(|step| step.is_ok()));
+        assert_eq!(input.run(&CrateMover9000).unwrap(), trace.into_stacks());
+    }
+
+    #[test]
+    fn test_trace_stops_at_first_error() {
+        let input: PuzzleInput =
+            "1 A B\n2 C\n\nmove 1 from 1 to 2\nmove 3 from 1 to 2\nmove 1 from 2 to 1\n"
+                .parse()
+                .unwrap();
+        let mut trace = input.trace(&CrateMover9000);
+        assert!(trace.next().unwrap().is_ok());
+        let error = trace.next().unwrap().unwrap_err();
+        assert_eq!(input.run(&CrateMover9000).unwrap_err(), error);
+        assert!(trace.next().is_none());
+        // The stacks are left as they were before the failed instruction.
+        assert_eq!(*trace.stacks().get(1).unwrap(), vec!['C', 'B']);
+    }
+
+    #[test]
+    fn test_trace_clamped_moves() {
+        let mut stacks: Stacks = "capacity 3\n1 A B C\n2 D E".parse().unwrap();
+        stacks.set_overflow_policy(OverflowPolicy::Clamp);
+        let instructions: CraneInstructions =
+            "move 3 from 1 to 2\nmove 2 from 1 to 2".parse().unwrap();
+        let steps: Vec<Step> = stacks
+            .trace(&CrateMover9001, &instructions)
+            .map(Result::unwrap)
+            .collect();
+        // Only the top crate fits on stack 2, and then nothing does.
+        assert_eq!(1, steps[0].instruction.num_to_move);
+        assert_eq!(vec!['C'], steps[0].moved);
+        assert_eq!(vec![2, 3], steps[0].heights);
+        assert_eq!(0, steps[1].instruction.num_to_move);
+        assert!(steps[1].moved.is_empty());
+    }
+
+    #[test]
+    fn test_trace_crane_without_landing() {
+        let input: PuzzleInput = "1 A B C\n2\n\nmove 2 from 1 to 2\nmove 2 from 1 to 1\n"
+            .parse()
+            .unwrap();
+        let steps: Vec<Step> = input.trace(&TopOnly).map(Result::unwrap).collect();
+        // Only the top crate lands, whatever was asked for.
+        assert_eq!(vec!['C'], steps[0].moved);
+        assert_eq!(vec![2, 1], steps[0].heights);
+        assert_eq!(vec!['A', 'B'], steps[1].moved);
+        assert_eq!(vec![2, 1], steps[1].heights);
+    }
+}
@@ -0,0 +1,397 @@
+//! Following individual crates through a list of instructions.
+//!
+//! In `Stacks` a crate is just its label, so two `S` crates can't be told
+//! apart. [`TrackedStacks`] gives every crate a [`CrateId`] when tracking
+//! starts, and records each move of each crate, so we can ask where a
+//! crate is now, how it got there, and which instructions moved it.
+
+use std::fmt::{self, Display};
+
+use crate::{
+    capacity::Capacity,
+    crane::{Crane, CraneInstruction, CraneInstructions, Landing},
+    error::{CraneError, InstructionFailure},
+    lift::{LiftLimit, Lifts},
+    model::{Stack, Stacks},
+};
+
+/// A crate's identity, which stays the same wherever it's moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CrateId(pub usize);
+
+impl Display for CrateId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Where a crate is. Both fields count from 0, and `position` counts up
+/// from the bottom of the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub stack: usize,
+    pub position: usize,
+}
+
+/// One move of one crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Movement {
+    /// Which instruction moved the crate, counting the instructions
+    /// applied since tracking started from 0. Errors from
+    /// `TrackedStacks::apply_instructions` count the same way.
+    pub index: usize,
+    pub instruction: CraneInstruction,
+    pub from: Location,
+    pub to: Location,
+}
+
+/// A set of stacks where every crate has an id and a history, created by
+/// [`Stacks::tracked`].
+#[derive(Debug, Clone)]
+pub struct TrackedStacks {
+    landing: Landing,
+    capacity: Capacity,
+    lift_limit: Option<LiftLimit>,
+    stacks: Vec<Vec<CrateId>>,
+    /// Indexed by id.
+    labels: Vec<char>,
+    locations: Vec<Location>,
+    histories: Vec<Vec<Movement>>,
+    applied: usize,
+}
+
+impl Stacks {
+    /// Start tracking these stacks as they're moved by `crane`. The crates are
+    /// numbered stack by stack, from the bottom up, starting at 0.
+    ///
+    /// Tracking needs to know how the crane lands groups of crates, so
+    /// this returns `None` if it doesn't have a `Landing`.
+    #[must_use]
+    pub fn tracked<C: Crane + ?Sized>(&self, crane: &C) -> Option<TrackedStacks> {
+        let mut tracked = TrackedStacks {
+            landing: crane.landing()?,
+            capacity: self.capacity().clone(),
+            lift_limit: crane.lift_limit().copied(),
+            stacks: Vec::with_capacity(self.len()),
+            labels: Vec::new(),
+            locations: Vec::new(),
+            histories: Vec::new(),
+            applied: 0,
+        };
+        for (stack, crates) in self.iter().enumerate() {
+            let mut ids = Vec::with_capacity(crates.len());
+            for (position, label) in crates.iter().enumerate() {
+                ids.push(CrateId(tracked.labels.len()));
+                tracked.labels.push(*label);
+                tracked.locations.push(Location { stack, position });
+                tracked.histories.push(Vec::new());
+            }
+            tracked.stacks.push(ids);
+        }
+        Some(tracked)
+    }
+}
+
+impl TrackedStacks {
+    /// The crate at `position` (from the bottom) of `stack`, if there is one.
+    #[must_use]
+    pub fn id_at(&self, stack: usize, position: usize) -> Option<CrateId> {
+        self.stacks.get(stack)?.get(position).copied()
+    }
+
+    /// The label of a crate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't one of these crates.
+    #[must_use]
+    pub fn label(&self, id: CrateId) -> char {
+        self.labels[id.0]
+    }
+
+    /// Where a crate is now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't one of these crates.
+    #[must_use]
+    pub fn location(&self, id: CrateId) -> Location {
+        self.locations[id.0]
+    }
+
+    /// Every move of a crate, oldest first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't one of these crates.
+    #[must_use]
+    pub fn history(&self, id: CrateId) -> &[Movement] {
+        &self.histories[id.0]
+    }
+
+    /// The indices of the instructions that moved a crate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't one of these crates.
+    pub fn touched_by(&self, id: CrateId) -> impl Iterator<Item = usize> + '_ {
+        self.history(id).iter().map(|movement| movement.index)
+    }
+
+    /// The number of instructions applied since tracking started.
+    #[must_use]
+    pub const fn applied(&self) -> usize {
+        self.applied
+    }
+
+    /// Apply a single instruction, recording where each moved crate went.
+    /// If the crane has a lift limit, the crates are recorded at each
+    /// stop of a split lift, all under the same instruction. If the
+    /// instruction is invalid nothing changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Stacks::apply_instruction`.
+    pub fn apply_in_place(&mut self, instruction: &CraneInstruction) -> Result<(), CraneError> {
+        let lifts = Lifts::plan(
+            instruction,
+            self.stacks.len(),
+            &self.capacity,
+            |stack| self.stacks[stack].len(),
+            Some(self.landing),
+            self.lift_limit.as_ref(),
+            |_| 0,
+        )?;
+        for lift in lifts.as_slice() {
+            self.lift(lift, &lifts.admitted);
+        }
+        self.applied += 1;
+        Ok(())
+    }
+
+    /// Make a single lift that's already been checked, recording the moves
+    /// as part of `instruction`.
+    fn lift(&mut self, lift: &CraneInstruction, instruction: &CraneInstruction) {
+        let from = &mut self.stacks[lift.from_stack];
+        let start = from.len() - lift.num_to_move;
+        let mut group: Vec<(CrateId, Location)> = from
+            .split_off(start)
+            .into_iter()
+            .map(|id| (id, self.locations[id.0]))
+            .collect();
+        if self.landing == Landing::Reversed {
+            group.reverse();
+        }
+
+        let to = &mut self.stacks[lift.to_stack];
+        for (id, from) in group {
+            let location = Location {
+                stack: lift.to_stack,
+                position: to.len(),
+            };
+            to.push(id);
+            self.locations[id.0] = location;
+            self.histories[id.0].push(Movement {
+                index: self.applied,
+                instruction: *instruction,
+                from,
+                to: location,
+            });
+        }
+    }
+
+    /// Apply each of these instructions in order, like
+    /// `Stacks::apply_instructions`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Stacks::apply_instructions`, with the
+    /// crates left as they were just before the failed instruction. The
+    /// index of the failed instruction counts the instructions applied
+    /// since tracking started, the same as `Movement::index`.
+    pub fn apply_instructions(
+        &mut self,
+        instructions: &CraneInstructions,
+    ) -> Result<(), CraneError> {
+        for instruction in instructions.iter() {
+            if let Err(cause) = self.apply_in_place(instruction) {
+                return Err(CraneError::Instruction(Box::new(InstructionFailure {
+                    index: self.applied,
+                    line: None,
+                    instruction: *instruction,
+                    before: self.to_stacks(),
+                    cause,
+                })));
+            }
+        }
+        Ok(())
+    }
+
+    /// The labels of the crates, as ordinary `Stacks`.
+    #[must_use]
+    pub fn to_stacks(&self) -> Stacks {
+        let mut stacks: Stacks = self
+            .stacks
+            .iter()
+            .map(|ids| Stack::from(ids.iter().map(|id| self.labels[id.0]).collect::<Vec<_>>()))
+            .collect();
+        stacks.set_capacity(self.capacity.clone());
+        stacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crane::{CraneModel, CrateMover9000, CrateMover9001},
+        test_util::TopOnly,
+    };
+
+    fn stacks(s: &str) -> Stacks {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_same_labels_are_different_crates() {
+        let start = stacks("1 S S\n2 A\n3 S");
+        let mut tracked = start.tracked(&CrateMover9001).unwrap();
+        let first = tracked.id_at(0, 0).unwrap();
+        let third = tracked.id_at(2, 0).unwrap();
+        assert_eq!(tracked.label(first), tracked.label(third));
+
+        let instructions: CraneInstructions =
+            "move 2 from 1 to 2\nmove 1 from 3 to 1\nmove 1 from 2 to 3"
+                .parse()
+                .unwrap();
+        tracked.apply_instructions(&instructions).unwrap();
+        assert_eq!(
+            Location {
+                stack: 1,
+                position: 1
+            },
+            tracked.location(first)
+        );
+        assert_eq!(
+            Location {
+                stack: 0,
+                position: 0
+            },
+            tracked.location(third)
+        );
+        assert_eq!(vec![0], tracked.touched_by(first).collect::<Vec<_>>());
+        assert_eq!(vec![1], tracked.touched_by(third).collect::<Vec<_>>());
+
+        let second = CrateId(1);
+        assert_eq!(vec![0, 2], tracked.touched_by(second).collect::<Vec<_>>());
+        let history = tracked.history(second);
+        assert_eq!(
+            Location {
+                stack: 0,
+                position: 1
+            },
+            history[0].from
+        );
+        assert_eq!(
+            Location {
+                stack: 1,
+                position: 2
+            },
+            history[0].to
+        );
+        assert_eq!(
+            Location {
+                stack: 2,
+                position: 0
+            },
+            history[1].to
+        );
+    }
+
+    #[test]
+    fn test_matches_untracked_stacks() {
+        let start = stacks("1 Z N\n2 M C D\n3 P");
+        let instructions: CraneInstructions = "move 1 from 2 to 1\n\
+                                               move 3 from 1 to 3\n\
+                                               move 2 from 2 to 2\n\
+                                               move 1 from 3 to 2"
+            .parse()
+            .unwrap();
+        for crane in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let mut tracked = start.tracked(&crane).unwrap();
+            tracked.apply_instructions(&instructions).unwrap();
+            let expected = start.clone().apply_instructions(&crane, &instructions);
+            assert_eq!(expected.unwrap(), tracked.to_stacks());
+
+            // Every crate's location agrees with where it is in the stacks.
+            for id in (0..6).map(CrateId) {
+                let Location { stack, position } = tracked.location(id);
+                assert_eq!(Some(id), tracked.id_at(stack, position));
+            }
+        }
+    }
+
+    #[test]
+    fn test_reversed_moves_record_positions() {
+        let mut tracked = stacks("1 A B C\n2").tracked(&CrateMover9000).unwrap();
+        tracked
+            .apply_in_place(&CraneInstruction {
+                num_to_move: 3,
+                from_stack: 0,
+                to_stack: 1,
+            })
+            .unwrap();
+        // The top crate, C, lands first at the bottom.
+        let c = CrateId(2);
+        let movement = tracked.history(c)[0];
+        assert_eq!(
+            Location {
+                stack: 0,
+                position: 2
+            },
+            movement.from
+        );
+        assert_eq!(
+            Location {
+                stack: 1,
+                position: 0
+            },
+            movement.to
+        );
+    }
+
+    #[test]
+    fn test_failed_instruction_changes_nothing() {
+        let start = stacks("1 A\n2 B");
+        let mut tracked = start.tracked(&CrateMover9000).unwrap();
+        let instructions: CraneInstructions =
+            "move 1 from 1 to 2\nmove 1 from 1 to 2".parse().unwrap();
+        let error = tracked.apply_instructions(&instructions).unwrap_err();
+        assert!(matches!(error.cause(), CraneError::InvalidMove { .. }));
+        assert_eq!(1, tracked.applied());
+        assert_eq!(stacks("1\n2 B A"), tracked.to_stacks());
+    }
+
+    #[test]
+    fn test_indices_count_from_the_start_of_tracking() {
+        let mut tracked = stacks("1 A B\n2 C").tracked(&CrateMover9000).unwrap();
+        let first: CraneInstructions = "move 1 from 1 to 2".parse().unwrap();
+        tracked.apply_instructions(&first).unwrap();
+        let second: CraneInstructions = "move 1 from 2 to 1\nmove 3 from 1 to 2".parse().unwrap();
+        let error = tracked.apply_instructions(&second).unwrap_err();
+        let CraneError::Instruction(failure) = error else {
+            panic!("expected an instruction failure, got {error:?}");
+        };
+        assert_eq!(2, failure.index);
+        let moved = tracked.id_at(0, 1).unwrap();
+        let indices: Vec<usize> = tracked.history(moved).iter().map(|m| m.index).collect();
+        assert_eq!(vec![0, 1], indices);
+        assert_eq!(2, tracked.applied());
+    }
+
+    // Without a `Landing` there's no way to tell where each crate went.
+    #[test]
+    fn test_crane_without_landing() {
+        assert!(stacks("1 A").tracked(&TopOnly).is_none());
+    }
+}